@@ -14,6 +14,10 @@ use sys::tty::get_tty;
 /// asyncronized from piped input would rarely make sense. In other words, if you pipe standard
 /// output from another process, it won't be reflected in the stream returned by this function, as
 /// this represents the TTY device, and not the piped standard input.
+///
+/// The thread keeps waiting for input after the reader is dropped, and takes the next byte that
+/// arrives. When querying the terminal (e.g. with `DetectTerminalColors`), use the same reader for
+/// successive queries, or a leftover thread would take the beginning of the next reply.
 pub fn async_stdin() -> AsyncReader {
     let (send, recv) = mpsc::channel();
 
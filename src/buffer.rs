@@ -0,0 +1,306 @@
+//! Double-buffered drawing.
+//!
+//! A `Buffer` holds the cells of the screen as they should look. Drawing happens in memory, and
+//! `flush` only writes the cells which changed since the last flush, moving the cursor and
+//! changing colors and styles only when needed. This avoids flickering, and keeps the output
+//! small.
+//!
+//! The buffer assumes that nothing else writes to the terminal between flushes. If something
+//! does, call `invalidate` to redraw everything on the next flush.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use termion::buffer::Buffer;
+//! use termion::color::{AnyColor, Rgb};
+//! use termion::raw::IntoRawMode;
+//! use termion::style::{Attributes, Style};
+//! use std::io::stdout;
+//!
+//! fn main() {
+//!     let mut stdout = stdout().into_raw_mode().unwrap();
+//!     let (cols, rows) = termion::terminal_size().unwrap();
+//!     let mut buffer = Buffer::new(cols, rows);
+//!
+//!     for i in 0..10 {
+//!         buffer.clear();
+//!         let style = Style::new().fg(AnyColor::Rgb(Rgb(255, 0, 0))).attrs(Attributes::BOLD);
+//!         buffer.print(i + 1, 1, "Hello", style);
+//!         buffer.flush(&mut stdout).unwrap();
+//!     }
+//! }
+//! ```
+
+use std::io::{self, Write};
+
+use clear;
+use color::AnyColor;
+use cursor;
+use style::{self, Attributes, Style};
+
+/// A single character cell.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cell {
+    /// The character displayed.
+    pub ch: char,
+    /// The foreground color.
+    pub fg: AnyColor,
+    /// The background color.
+    pub bg: AnyColor,
+    /// The text attributes.
+    pub attrs: Attributes,
+}
+
+impl Cell {
+    /// Get the style of this cell.
+    pub fn style(&self) -> Style {
+        Style {
+            fg: Some(self.fg),
+            bg: Some(self.bg),
+            attrs: self.attrs,
+        }
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Cell {
+        Cell {
+            ch: ' ',
+            fg: AnyColor::Reset,
+            bg: AnyColor::Reset,
+            attrs: Attributes::empty(),
+        }
+    }
+}
+
+/// A double-buffered screen.
+///
+/// See the module documentation for details.
+#[derive(Clone, Debug)]
+pub struct Buffer {
+    cols: u16,
+    rows: u16,
+    /// The cells being drawn.
+    back: Vec<Cell>,
+    /// The cells currently displayed by the terminal.
+    front: Vec<Cell>,
+    /// Whether the terminal contents are unknown, and must be entirely redrawn.
+    invalid: bool,
+    /// The zero-based position of the terminal cursor, if known.
+    cursor: Option<(u16, u16)>,
+    /// The current style of the terminal, if known.
+    pen: Option<Style>,
+}
+
+impl Buffer {
+    /// Create a blank buffer of the given size, in columns and rows.
+    ///
+    /// The first flush redraws the entire screen.
+    pub fn new(cols: u16, rows: u16) -> Buffer {
+        let len = cols as usize * rows as usize;
+        Buffer {
+            cols: cols,
+            rows: rows,
+            back: vec![Cell::default(); len],
+            front: vec![Cell::default(); len],
+            invalid: true,
+            cursor: None,
+            pen: None,
+        }
+    }
+
+    /// Get the size of the buffer, in columns and rows.
+    pub fn size(&self) -> (u16, u16) {
+        (self.cols, self.rows)
+    }
+
+    /// Change the size of the buffer, e.g. after the terminal was resized.
+    ///
+    /// The content is cleared, and the next flush redraws the entire screen.
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        *self = Buffer::new(cols, rows);
+    }
+
+    /// Get the cell at the given (1,1)-based position.
+    pub fn get(&self, x: u16, y: u16) -> Option<&Cell> {
+        self.index(x, y).map(move |i| &self.back[i])
+    }
+
+    /// Get a mutable reference to the cell at the given (1,1)-based position.
+    pub fn get_mut(&mut self, x: u16, y: u16) -> Option<&mut Cell> {
+        match self.index(x, y) {
+            Some(i) => Some(&mut self.back[i]),
+            None => None,
+        }
+    }
+
+    /// Set the cell at the given (1,1)-based position.
+    ///
+    /// Positions outside of the buffer are ignored.
+    pub fn set(&mut self, x: u16, y: u16, cell: Cell) {
+        if let Some(c) = self.get_mut(x, y) {
+            *c = cell;
+        }
+    }
+
+    /// Write some text starting at the given (1,1)-based position.
+    ///
+    /// Every character takes up one cell. The text is clipped at the end of the line. Unset
+    /// colors of the style are the default colors.
+    pub fn print(&mut self, x: u16, y: u16, text: &str, style: Style) {
+        for (i, ch) in text.chars().enumerate() {
+            if x as usize + i > self.cols as usize {
+                break;
+            }
+            self.set(x + i as u16,
+                     y,
+                     Cell {
+                         ch: ch,
+                         fg: style.fg.unwrap_or(AnyColor::Reset),
+                         bg: style.bg.unwrap_or(AnyColor::Reset),
+                         attrs: style.attrs,
+                     });
+        }
+    }
+
+    /// Reset every cell to a blank one.
+    pub fn clear(&mut self) {
+        for cell in &mut self.back {
+            *cell = Cell::default();
+        }
+    }
+
+    /// Forget what the terminal displays, so that the next flush redraws the entire screen.
+    pub fn invalidate(&mut self) {
+        self.invalid = true;
+        self.cursor = None;
+        self.pen = None;
+    }
+
+    /// Write the changes since the last flush to the terminal.
+    pub fn flush<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        if self.invalid {
+            write!(out, "{}{}", style::Reset, clear::All)?;
+            for cell in &mut self.front {
+                *cell = Cell::default();
+            }
+            self.pen = Some(Style::new());
+            self.invalid = false;
+        }
+
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let i = y as usize * self.cols as usize + x as usize;
+                let cell = self.back[i];
+                if cell == self.front[i] {
+                    continue;
+                }
+
+                if self.cursor != Some((x, y)) {
+                    write!(out, "{}", cursor::Goto(x + 1, y + 1))?;
+                }
+                self.write_pen(out, cell.style())?;
+                let ch = if cell.ch.is_control() { ' ' } else { cell.ch };
+                write!(out, "{}", ch)?;
+
+                self.front[i] = cell;
+                // On the last column, the cursor doesn't move, and the next character wraps.
+                self.cursor = if x + 1 < self.cols { Some((x + 1, y)) } else { None };
+            }
+        }
+
+        out.flush()
+    }
+
+    /// Change the style of the terminal, if needed.
+    fn write_pen<W: Write>(&mut self, out: &mut W, style: Style) -> io::Result<()> {
+        match self.pen {
+            Some(pen) => write!(out, "{}", pen.transition(&style))?,
+            None => write!(out, "{}{}", style::Reset, Style::new().transition(&style))?,
+        }
+        self.pen = Some(style);
+        Ok(())
+    }
+
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        if x == 0 || y == 0 || x > self.cols || y > self.rows {
+            None
+        } else {
+            Some((y - 1) as usize * self.cols as usize + (x - 1) as usize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use color::{AnsiValue, Rgb};
+    use vt::VirtualTerminal;
+
+    #[test]
+    fn test_render() {
+        let mut buffer = Buffer::new(10, 3);
+        let mut term = VirtualTerminal::new(10, 3);
+        let red = AnyColor::Rgb(Rgb(255, 0, 0));
+        let blue = AnyColor::Ansi(AnsiValue(4));
+
+        buffer.print(2, 1, "Hello", Style::new().fg(red).bg(blue).attrs(Attributes::BOLD));
+        buffer.print(1, 3, "World, clipped", Style::new().attrs(Attributes::ITALIC));
+        buffer.flush(&mut term).unwrap();
+
+        assert_eq!(term.contents(), " Hello\n\nWorld, cli");
+        for y in 1..4 {
+            for x in 1..11 {
+                assert_eq!(term.cell(x, y), buffer.get(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_minimal_output() {
+        let mut buffer = Buffer::new(10, 3);
+        buffer.print(1, 1, "abc", Style::new());
+
+        let mut out = Vec::new();
+        buffer.flush(&mut out).unwrap();
+
+        // Nothing changed.
+        out.clear();
+        buffer.flush(&mut out).unwrap();
+        assert!(out.is_empty());
+
+        // Only the changed cell is written, after moving the cursor.
+        buffer.set(2, 1, Cell { ch: 'x', ..Cell::default() });
+        buffer.flush(&mut out).unwrap();
+        assert_eq!(out, b"\x1B[1;2Hx");
+
+        // Adjacent cells don't need cursor movement, and unchanged colors are not repeated.
+        out.clear();
+        let red = AnyColor::Ansi(AnsiValue(1));
+        buffer.print(5, 2, "de", Style::new().fg(red));
+        buffer.flush(&mut out).unwrap();
+        assert_eq!(out, b"\x1B[2;5H\x1B[38;5;1mde");
+
+        // Only the changed attribute is switched off.
+        out.clear();
+        buffer.print(5, 2, "d", Style::new().fg(red).attrs(Attributes::BOLD));
+        buffer.flush(&mut out).unwrap();
+        buffer.print(5, 2, "d", Style::new().fg(red));
+        out.clear();
+        buffer.flush(&mut out).unwrap();
+        assert_eq!(out, b"\x1B[2;5H\x1B[22md");
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let mut buffer = Buffer::new(5, 2);
+        let mut term = VirtualTerminal::new(5, 2);
+        buffer.print(1, 1, "abc", Style::new());
+        buffer.flush(&mut term).unwrap();
+
+        write!(term, "{}garbage", cursor::Goto(1, 1)).unwrap();
+        buffer.invalidate();
+        buffer.flush(&mut term).unwrap();
+        assert_eq!(term.contents(), "abc");
+    }
+}
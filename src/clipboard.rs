@@ -0,0 +1,198 @@
+//! Clipboard access (OSC 52).
+//!
+//! This works through the terminal, so it also works over ssh, copying to the clipboard of the
+//! machine the terminal runs on. Most terminals support setting the clipboard, but reading it is
+//! often disabled for security reasons.
+//!
+//! # Example
+//!
+//! ```rust
+//! use termion::clipboard::{Selection, SetClipboard};
+//!
+//! fn main() {
+//!     print!("{}", SetClipboard(Selection::Clipboard, "Copied!"));
+//! }
+//! ```
+
+use std::fmt;
+use std::io::{self, Error, ErrorKind, Read, Write};
+use std::str;
+use osc;
+
+/// A selection to access.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Selection {
+    /// The clipboard, used by the copy and paste commands.
+    Clipboard,
+    /// The primary selection, i.e. the selected text (X11 and Wayland only).
+    Primary,
+}
+
+impl Selection {
+    fn param(self) -> char {
+        match self {
+            Selection::Clipboard => 'c',
+            Selection::Primary => 'p',
+        }
+    }
+}
+
+/// Set the contents of a selection.
+///
+/// Note that some terminals limit the size of the data.
+#[derive(Copy, Clone, Debug)]
+pub struct SetClipboard<T: AsRef<[u8]>>(pub Selection, pub T);
+
+impl<T: AsRef<[u8]>> fmt::Display for SetClipboard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, osc!("52;{};{}"), self.0.param(), encode(self.1.as_ref()))
+    }
+}
+
+/// Clear a selection.
+#[derive(Copy, Clone, Debug)]
+pub struct ClearClipboard(pub Selection);
+
+impl fmt::Display for ClearClipboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Invalid base64 data clears the selection.
+        write!(f, osc!("52;{};!"), self.0.param())
+    }
+}
+
+/// Types that allow reading the clipboard from the terminal.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use termion::async_stdin;
+/// use termion::clipboard::{ReadClipboard, Selection};
+/// use termion::raw::IntoRawMode;
+/// use std::io::stdout;
+///
+/// fn main() {
+///     let mut stdout = stdout().into_raw_mode().unwrap();
+///     let mut stdin = async_stdin();
+///
+///     let clipboard = stdout.read_clipboard(&mut stdin, Selection::Clipboard).unwrap();
+///     let primary = stdout.read_clipboard(&mut stdin, Selection::Primary).unwrap();
+/// }
+/// ```
+pub trait ReadClipboard {
+    /// Get the contents of a selection, reading the reply of the terminal from `stdin`.
+    ///
+    /// `stdin` is usually an `async_stdin`, reused across queries.
+    ///
+    /// This fails if the terminal doesn't answer, which is the case for most terminals unless
+    /// explicitly allowed by the user.
+    fn read_clipboard<R: Read>(&mut self, stdin: &mut R, selection: Selection)
+                               -> io::Result<Vec<u8>>;
+}
+
+impl<W: Write> ReadClipboard for W {
+    fn read_clipboard<R: Read>(&mut self, stdin: &mut R, selection: Selection)
+                               -> io::Result<Vec<u8>> {
+        let query = format!(osc!("52;{};?"), selection.param());
+        let reply = osc::query(self, stdin, &query)?;
+        parse_reply(&reply).ok_or_else(|| Error::new(ErrorKind::Other, "Invalid clipboard reply."))
+    }
+}
+
+/// Parse a `52 ; selection ; data` reply.
+fn parse_reply(reply: &[u8]) -> Option<Vec<u8>> {
+    let reply = str::from_utf8(reply).ok()?;
+    let mut parts = reply.splitn(3, ';');
+
+    if parts.next()? != "52" {
+        return None;
+    }
+    parts.next()?;
+    decode(parts.next()?)
+}
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode some data in base64, with padding.
+fn encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len() * 4 / 3 + 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode some base64 data, with optional padding.
+fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim_end_matches('=').as_bytes();
+    if encoded.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut data = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.chunks(4) {
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|&a| a == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            data.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        let cases: &[(&[u8], &str)] = &[(b"", ""),
+                                        (b"f", "Zg=="),
+                                        (b"fo", "Zm8="),
+                                        (b"foo", "Zm9v"),
+                                        (b"foob", "Zm9vYg=="),
+                                        (b"\xFF\x00\xFE", "/wD+")];
+        for &(data, encoded) in cases {
+            assert_eq!(encode(data), encoded);
+            assert_eq!(decode(encoded).unwrap(), data);
+        }
+        assert_eq!(decode("Zm8").unwrap(), b"fo");
+        assert_eq!(decode("Z"), None);
+        assert_eq!(decode("Zm!v"), None);
+    }
+
+    #[test]
+    fn test_sequences() {
+        assert_eq!(SetClipboard(Selection::Clipboard, "foo").to_string(),
+                   "\x1B]52;c;Zm9v\x1B\\");
+        assert_eq!(SetClipboard(Selection::Primary, vec![]).to_string(), "\x1B]52;p;\x1B\\");
+        assert_eq!(ClearClipboard(Selection::Clipboard).to_string(), "\x1B]52;c;!\x1B\\");
+    }
+
+    #[test]
+    fn test_read_clipboard() {
+        let mut out = Vec::new();
+        let mut stdin = &b"\x1B]52;c;Zm9v\x07\x1B]52;p;YmFy\x1B\\"[..];
+
+        assert_eq!(out.read_clipboard(&mut stdin, Selection::Clipboard).unwrap(), b"foo");
+        assert_eq!(out.read_clipboard(&mut stdin, Selection::Primary).unwrap(), b"bar");
+        assert_eq!(out, b"\x1B]52;c;?\x1B\\\x1B]52;p;?\x1B\\");
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply(b"52;c;Zm9v").unwrap(), b"foo");
+        assert_eq!(parse_reply(b"52;;").unwrap(), b"");
+        assert_eq!(parse_reply(b"11;c;Zm9v"), None);
+        assert_eq!(parse_reply(b"52;c;!"), None);
+    }
+}
@@ -11,13 +11,36 @@
 //!     println!("{}Back again", color::Fg(color::Reset));
 //! }
 //! ```
+//!
+//! # Disabling colors
+//!
+//! Colors and styles are written unconditionally by default. Command-line tools should instead
+//! follow the `NO_COLOR` and `CLICOLOR` conventions, using `ColorChoice::Auto`:
+//!
+//! ```rust
+//! use termion::color::{self, ColorChoice};
+//!
+//! fn main() {
+//!     // E.g. from a `--color=auto|always|never` option.
+//!     color::set_color_choice("never".parse().unwrap());
+//!     assert_eq!(color::Fg(color::Red).to_string(), "");
+//!
+//!     color::set_color_choice(ColorChoice::Auto);
+//!     println!("{}Red only on a terminal", color::Fg(color::Red));
+//! }
+//! ```
 
 use std::fmt;
-use raw::CONTROL_SEQUENCE_TIMEOUT;
 use std::io::{self, Write, Read};
-use std::time::{SystemTime, Duration};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::ops;
+use terminfo::Terminfo;
 use async::async_stdin;
 use std::env;
+use std::str;
+use osc;
+use sys::tty::is_tty;
 
 /// A terminal color.
 pub trait Color {
@@ -25,44 +48,94 @@ pub trait Color {
     fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result;
     /// Write the background version of this color.
     fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result;
+
+    /// Write the underline version of this color.
+    ///
+    /// The default implementation derives it from the foreground version.
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        /// Display the foreground version of a color.
+        struct AsFg<'a, C: Color + ?Sized + 'a>(&'a C);
+
+        impl<'a, C: Color + ?Sized> fmt::Display for AsFg<'a, C> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.write_fg(f)
+            }
+        }
+
+        match fg_to_underline(&AsFg(self).to_string()) {
+            Some(params) => write!(f, csi!("{}m"), params),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Convert the SGR sequence of a foreground color to the parameters of the underline color.
+fn fg_to_underline(fg: &str) -> Option<String> {
+    if !fg.starts_with("\x1B[") || !fg.ends_with('m') {
+        return None;
+    }
+    let params = &fg[2..fg.len() - 1];
+
+    if params == "39" {
+        Some("59".to_owned())
+    } else if params.starts_with("38;") || params.starts_with("38:") {
+        Some(format!("58{}", &params[2..]))
+    } else {
+        match params.parse::<u8>() {
+            Ok(n @ 30..=37) => Some(format!("58;5;{}", n - 30)),
+            Ok(n @ 90..=97) => Some(format!("58;5;{}", n - 90 + 8)),
+            _ => None,
+        }
+    }
 }
 
 macro_rules! derive_color {
-    ($doc:expr, $name:ident, $value:expr) => {
+    ($doc:expr, $name:ident, $fg:expr, $bg:expr, $value:expr) => {
         #[doc = $doc]
-        #[derive(Copy, Clone, Debug)]
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
         pub struct $name;
 
+        impl From<$name> for AnyColor {
+            fn from(_: $name) -> AnyColor {
+                AnyColor::$name
+            }
+        }
+
         impl Color for $name {
             #[inline]
             fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
-                write!(f, csi!("38;5;", $value, "m"))
+                write!(f, csi!($fg, "m"))
             }
 
             #[inline]
             fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
-                write!(f, csi!("48;5;", $value, "m"))
+                write!(f, csi!($bg, "m"))
+            }
+
+            #[inline]
+            fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, csi!("58;5;", $value, "m"))
             }
         }
     };
 }
 
-derive_color!("Black.", Black, "0");
-derive_color!("Red.", Red, "1");
-derive_color!("Green.", Green, "2");
-derive_color!("Yellow.", Yellow, "3");
-derive_color!("Blue.", Blue, "4");
-derive_color!("Magenta.", Magenta, "5");
-derive_color!("Cyan.", Cyan, "6");
-derive_color!("White.", White, "7");
-derive_color!("High-intensity light black.", LightBlack, "8");
-derive_color!("High-intensity light red.", LightRed, "9");
-derive_color!("High-intensity light green.", LightGreen, "10");
-derive_color!("High-intensity light yellow.", LightYellow, "11");
-derive_color!("High-intensity light blue.", LightBlue, "12");
-derive_color!("High-intensity light magenta.", LightMagenta, "13");
-derive_color!("High-intensity light cyan.", LightCyan, "14");
-derive_color!("High-intensity light white.", LightWhite, "15");
+derive_color!("Black.", Black, "30", "40", "0");
+derive_color!("Red.", Red, "31", "41", "1");
+derive_color!("Green.", Green, "32", "42", "2");
+derive_color!("Yellow.", Yellow, "33", "43", "3");
+derive_color!("Blue.", Blue, "34", "44", "4");
+derive_color!("Magenta.", Magenta, "35", "45", "5");
+derive_color!("Cyan.", Cyan, "36", "46", "6");
+derive_color!("White.", White, "37", "47", "7");
+derive_color!("High-intensity light black.", LightBlack, "90", "100", "8");
+derive_color!("High-intensity light red.", LightRed, "91", "101", "9");
+derive_color!("High-intensity light green.", LightGreen, "92", "102", "10");
+derive_color!("High-intensity light yellow.", LightYellow, "93", "103", "11");
+derive_color!("High-intensity light blue.", LightBlue, "94", "104", "12");
+derive_color!("High-intensity light magenta.", LightMagenta, "95", "105", "13");
+derive_color!("High-intensity light cyan.", LightCyan, "96", "106", "14");
+derive_color!("High-intensity light white.", LightWhite, "97", "107", "15");
 
 impl<'a> Color for &'a Color {
     #[inline]
@@ -74,10 +147,19 @@ impl<'a> Color for &'a Color {
     fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
         (*self).write_bg(f)
     }
+
+    #[inline]
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (*self).write_underline(f)
+    }
 }
 
 /// An arbitrary ANSI color value.
-#[derive(Clone, Copy, Debug)]
+///
+/// This always uses the 256-color sequences, even for the 16 basic colors (e.g. `38;5;1`). The
+/// named color types, such as `Red`, use the basic sequences (e.g. `31`), which are also
+/// understood by 8- and 16-color terminals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct AnsiValue(pub u8);
 
 impl AnsiValue {
@@ -108,8 +190,46 @@ impl AnsiValue {
 
         AnsiValue(0xE8 + shade)
     }
+
+    /// Get the RGB value of this color, assuming the default palette of xterm.
+    pub fn to_rgb(self) -> Rgb {
+        match self.0 {
+            n @ 0..=15 => BASIC_PALETTE[n as usize],
+            n @ 16..=231 => {
+                let n = n - 16;
+                Rgb(CUBE_LEVELS[(n / 36) as usize],
+                    CUBE_LEVELS[(n / 6 % 6) as usize],
+                    CUBE_LEVELS[(n % 6) as usize])
+            }
+            n => {
+                let level = 8 + 10 * (n - 232);
+                Rgb(level, level, level)
+            }
+        }
+    }
 }
 
+/// The default values of the 16 basic colors in xterm.
+const BASIC_PALETTE: [Rgb; 16] = [Rgb(0, 0, 0),
+                                  Rgb(205, 0, 0),
+                                  Rgb(0, 205, 0),
+                                  Rgb(205, 205, 0),
+                                  Rgb(0, 0, 238),
+                                  Rgb(205, 0, 205),
+                                  Rgb(0, 205, 205),
+                                  Rgb(229, 229, 229),
+                                  Rgb(127, 127, 127),
+                                  Rgb(255, 0, 0),
+                                  Rgb(0, 255, 0),
+                                  Rgb(255, 255, 0),
+                                  Rgb(92, 92, 255),
+                                  Rgb(255, 0, 255),
+                                  Rgb(0, 255, 255),
+                                  Rgb(255, 255, 255)];
+
+/// The levels of each component in the 6x6x6 color cube.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
 impl Color for AnsiValue {
     #[inline]
     fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -120,12 +240,209 @@ impl Color for AnsiValue {
     fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, csi!("48;5;{}m"), self.0)
     }
+
+    #[inline]
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("58;5;{}m"), self.0)
+    }
 }
 
 /// A truecolor RGB.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Rgb(pub u8, pub u8, pub u8);
 
+impl Rgb {
+    /// Get the nearest color of the 6x6x6 color cube and the grayscale ramp (`AnsiValue` 16 to
+    /// 255).
+    ///
+    /// The 16 basic colors are not considered, as their values depend on the terminal.
+    pub fn to_ansi256(self) -> AnsiValue {
+        fn level(c: u8) -> u8 {
+            match c {
+                0..=47 => 0,
+                48..=114 => 1,
+                c => (c - 35) / 40,
+            }
+        }
+
+        let Rgb(r, g, b) = self;
+        let cube = AnsiValue::rgb(level(r), level(g), level(b));
+
+        let average = (r as u32 + g as u32 + b as u32) / 3;
+        let shade = if average < 8 { 0 } else { ((average - 3) / 10).min(23) as u8 };
+        let gray = AnsiValue::grayscale(shade);
+
+        if self.distance(gray.to_rgb()) < self.distance(cube.to_rgb()) {
+            gray
+        } else {
+            cube
+        }
+    }
+
+    /// Get the nearest of the 16 named colors, assuming the default palette of xterm.
+    pub fn to_ansi16(self) -> AnyColor {
+        self.nearest_basic(16)
+    }
+
+    /// Get the hue (in degrees, from 0 to 360), saturation and lightness (from 0 to 1) of this
+    /// color.
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (h, max, min) = self.hue_max_min();
+        let l = (max + min) / 2.0;
+        let s = if max == min { 0.0 } else { (max - min) / (1.0 - (2.0 * l - 1.0).abs()) };
+        (h, s, l)
+    }
+
+    /// Create a color from its hue (in degrees), saturation and lightness (from 0 to 1).
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Rgb {
+        let s = s.clamp(0.0, 1.0);
+        let l = l.clamp(0.0, 1.0);
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Rgb::from_hue_chroma(h, chroma, l - chroma / 2.0)
+    }
+
+    /// Get the hue (in degrees, from 0 to 360), saturation and value (from 0 to 1) of this color.
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (h, max, min) = self.hue_max_min();
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+        (h, s, max)
+    }
+
+    /// Create a color from its hue (in degrees), saturation and value (from 0 to 1).
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Rgb {
+        let s = s.clamp(0.0, 1.0);
+        let v = v.clamp(0.0, 1.0);
+        let chroma = v * s;
+        Rgb::from_hue_chroma(h, chroma, v - chroma)
+    }
+
+    /// Get the hue, and the largest and smallest components (from 0 to 1).
+    fn hue_max_min(self) -> (f32, f32, f32) {
+        let (r, g, b) = (self.0 as f32 / 255.0, self.1 as f32 / 255.0, self.2 as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let h = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        (h, max, min)
+    }
+
+    /// Create a color from its hue, chroma, and the value to add to every component.
+    fn from_hue_chroma(h: f32, chroma: f32, m: f32) -> Rgb {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u8 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let component = |c: f32| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Rgb(component(r), component(g), component(b))
+    }
+
+    /// Interpolate linearly between this color (`t = 0`) and `other` (`t = 1`), component by
+    /// component.
+    pub fn lerp(self, other: Rgb, t: f32) -> Rgb {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Rgb(mix(self.0, other.0), mix(self.1, other.1), mix(self.2, other.2))
+    }
+
+    /// Interpolate between this color (`t = 0`) and `other` (`t = 1`) in the Oklab color space.
+    ///
+    /// Unlike `lerp`, the steps look evenly spaced, and the colors in between don't get muddy
+    /// (e.g. from red to green, through dark yellow rather than brown).
+    pub fn lerp_perceptual(self, other: Rgb, t: f32) -> Rgb {
+        if t <= 0.0 {
+            return self;
+        } else if t >= 1.0 {
+            return other;
+        }
+
+        let (a, b) = (self.to_oklab(), other.to_oklab());
+        let t = t as f64;
+        Rgb::from_oklab([a[0] + (b[0] - a[0]) * t,
+                         a[1] + (b[1] - a[1]) * t,
+                         a[2] + (b[2] - a[2]) * t])
+    }
+
+    /// Make the color lighter, by adding `amount` (from 0 to 1) to its HSL lightness.
+    pub fn lighten(self, amount: f32) -> Rgb {
+        let (h, s, l) = self.to_hsl();
+        Rgb::from_hsl(h, s, l + amount)
+    }
+
+    /// Make the color darker, by removing `amount` (from 0 to 1) from its HSL lightness.
+    pub fn darken(self, amount: f32) -> Rgb {
+        self.lighten(-amount)
+    }
+
+    /// Draw this color with the given opacity (from 0 to 1) over `background`.
+    pub fn blend(self, background: Rgb, alpha: f32) -> Rgb {
+        background.lerp(self, alpha)
+    }
+
+    /// Convert to the Oklab color space.
+    fn to_oklab(self) -> [f64; 3] {
+        fn linear(c: u8) -> f64 {
+            let c = c as f64 / 255.0;
+            if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        }
+
+        let (r, g, b) = (linear(self.0), linear(self.1), linear(self.2));
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+        [0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+         1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+         0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s]
+    }
+
+    /// Convert from the Oklab color space.
+    fn from_oklab(lab: [f64; 3]) -> Rgb {
+        fn gamma(c: f64) -> u8 {
+            let c = if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+            (c * 255.0).round().clamp(0.0, 255.0) as u8
+        }
+
+        let l = (lab[0] + 0.3963377774 * lab[1] + 0.2158037573 * lab[2]).powi(3);
+        let m = (lab[0] - 0.1055613458 * lab[1] - 0.0638541728 * lab[2]).powi(3);
+        let s = (lab[0] - 0.0894841775 * lab[1] - 1.2914855480 * lab[2]).powi(3);
+
+        Rgb(gamma(4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s),
+            gamma(-1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s),
+            gamma(-0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s))
+    }
+
+    /// Get the nearest of the first `count` named colors.
+    fn nearest_basic(self, count: usize) -> AnyColor {
+        let nearest = (0..count).min_by_key(|&i| self.distance(BASIC_PALETTE[i])).unwrap_or(0);
+        AnyColor::named(nearest as u8)
+    }
+
+    /// A fast approximation of the perceptual distance between two colors (the "redmean"
+    /// weighted euclidean distance, squared).
+    fn distance(self, other: Rgb) -> u32 {
+        let mean = (self.0 as i32 + other.0 as i32) / 2;
+        let dr = self.0 as i32 - other.0 as i32;
+        let dg = self.1 as i32 - other.1 as i32;
+        let db = self.2 as i32 - other.2 as i32;
+        (((512 + mean) * dr * dr) / 256 + 4 * dg * dg + ((767 - mean) * db * db) / 256) as u32
+    }
+}
+
 impl Color for Rgb {
     #[inline]
     fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -136,10 +453,101 @@ impl Color for Rgb {
     fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, csi!("48;2;{};{};{}m"), self.0, self.1, self.2)
     }
+
+    #[inline]
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("58;2;{};{};{}m"), self.0, self.1, self.2)
+    }
+}
+
+/// A gradient between several colors, evenly spaced.
+///
+/// The colors in between are interpolated with `Rgb::lerp_perceptual`.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color::{self, Gradient, Rgb};
+///
+/// fn main() {
+///     let heat = Gradient::new(&[Rgb(0, 0, 255), Rgb(255, 255, 0), Rgb(255, 0, 0)]);
+///     for color in heat.steps(40) {
+///         print!("{} ", color::Bg(color));
+///     }
+///     println!("{}", color::Bg(color::Reset));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Gradient {
+    stops: Vec<Rgb>,
+}
+
+impl Gradient {
+    /// Create a gradient going through the given colors, in order.
+    ///
+    /// # Panics
+    ///
+    /// This panics if there are no colors.
+    pub fn new(stops: &[Rgb]) -> Gradient {
+        assert!(!stops.is_empty(), "A gradient needs at least one color.");
+        Gradient { stops: stops.to_vec() }
+    }
+
+    /// Get the color at the position `t`, from 0 (the first color) to 1 (the last color).
+    pub fn at(&self, t: f32) -> Rgb {
+        let segments = self.stops.len() - 1;
+        if segments == 0 || t <= 0.0 {
+            return self.stops[0];
+        } else if t >= 1.0 {
+            return self.stops[segments];
+        }
+
+        let position = t * segments as f32;
+        let i = (position as usize).min(segments - 1);
+        self.stops[i].lerp_perceptual(self.stops[i + 1], position - i as f32)
+    }
+
+    /// Get `n` evenly spaced colors, from the first color to the last one.
+    pub fn steps(&self, n: usize) -> GradientSteps<'_> {
+        GradientSteps {
+            gradient: self,
+            i: 0,
+            n: n,
+        }
+    }
+}
+
+/// An iterator over evenly spaced colors of a gradient.
+///
+/// This is obtained through `Gradient::steps`.
+#[derive(Debug, Clone)]
+pub struct GradientSteps<'a> {
+    gradient: &'a Gradient,
+    i: usize,
+    n: usize,
+}
+
+impl<'a> Iterator for GradientSteps<'a> {
+    type Item = Rgb;
+
+    fn next(&mut self) -> Option<Rgb> {
+        if self.i >= self.n {
+            return None;
+        }
+        let t = if self.n == 1 { 0.0 } else { self.i as f32 / (self.n - 1) as f32 };
+        self.i += 1;
+        Some(self.gradient.at(t))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n - self.i, Some(self.n - self.i))
+    }
 }
 
+impl<'a> ExactSizeIterator for GradientSteps<'a> {}
+
 /// Reset colors to defaults.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Reset;
 
 impl Color for Reset {
@@ -152,6 +560,452 @@ impl Color for Reset {
     fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, csi!("49m"))
     }
+
+    #[inline]
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("59m"))
+    }
+}
+
+/// A color chosen at runtime.
+///
+/// Unlike the other color types, which are distinct types, this can hold any of them in a single
+/// value, e.g. to store colors in a data structure, without resorting to `&Color` trait objects.
+/// Every color type converts into it:
+///
+/// ```rust
+/// use termion::color::{self, AnyColor, AnsiValue};
+///
+/// let warning = true;
+/// let color: AnyColor = if warning { color::Yellow.into() } else { AnsiValue(244).into() };
+/// println!("{}Status", color::Fg(color));
+/// ```
+///
+/// Colors can be parsed from strings, and displayed back in the same format, e.g. to read them
+/// from configuration files:
+///
+/// - `reset` (or `default`) for the default color,
+/// - the named colors: `red`, `bright-red` (or `light-red`)...
+/// - `ansi(208)` (or just `208`) for an `AnsiValue`,
+/// - `#ff8800`, `#f80`, `rgb(255, 136, 0)` or the X11 form `rgb:ff/88/00` for an `Rgb`,
+/// - `grey0` to `grey100` (or `gray...`) for the X11 shades of gray, as an `Rgb`.
+///
+/// ```rust
+/// use termion::color::{AnyColor, Rgb};
+///
+/// let color: AnyColor = "rgb(255, 136, 0)".parse().unwrap();
+/// assert_eq!(color, AnyColor::Rgb(Rgb(255, 136, 0)));
+/// assert_eq!(color.to_string(), "#ff8800");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AnyColor {
+    /// The default color.
+    #[default]
+    Reset,
+    /// Black.
+    Black,
+    /// Red.
+    Red,
+    /// Green.
+    Green,
+    /// Yellow.
+    Yellow,
+    /// Blue.
+    Blue,
+    /// Magenta.
+    Magenta,
+    /// Cyan.
+    Cyan,
+    /// White.
+    White,
+    /// High-intensity light black.
+    LightBlack,
+    /// High-intensity light red.
+    LightRed,
+    /// High-intensity light green.
+    LightGreen,
+    /// High-intensity light yellow.
+    LightYellow,
+    /// High-intensity light blue.
+    LightBlue,
+    /// High-intensity light magenta.
+    LightMagenta,
+    /// High-intensity light cyan.
+    LightCyan,
+    /// High-intensity light white.
+    LightWhite,
+    /// An ANSI color value.
+    Ansi(AnsiValue),
+    /// A truecolor RGB.
+    Rgb(Rgb),
+}
+
+/// The named colors, in the order of their ANSI values.
+const NAMED: [AnyColor; 16] = [AnyColor::Black,
+                                 AnyColor::Red,
+                                 AnyColor::Green,
+                                 AnyColor::Yellow,
+                                 AnyColor::Blue,
+                                 AnyColor::Magenta,
+                                 AnyColor::Cyan,
+                                 AnyColor::White,
+                                 AnyColor::LightBlack,
+                                 AnyColor::LightRed,
+                                 AnyColor::LightGreen,
+                                 AnyColor::LightYellow,
+                                 AnyColor::LightBlue,
+                                 AnyColor::LightMagenta,
+                                 AnyColor::LightCyan,
+                                 AnyColor::LightWhite];
+
+/// The names of the named colors, as parsed and displayed.
+const NAMES: [&str; 16] = ["black",
+                           "red",
+                           "green",
+                           "yellow",
+                           "blue",
+                           "magenta",
+                           "cyan",
+                           "white",
+                           "bright-black",
+                           "bright-red",
+                           "bright-green",
+                           "bright-yellow",
+                           "bright-blue",
+                           "bright-magenta",
+                           "bright-cyan",
+                           "bright-white"];
+
+impl AnyColor {
+    /// Get the ANSI value of a named color.
+    fn named_index(&self) -> Option<u8> {
+        NAMED.iter().position(|c| c == self).map(|i| i as u8)
+    }
+
+    /// Get the color type (e.g. `Red`) of a named color.
+    fn as_named(&self) -> Option<&'static dyn Color> {
+        Some(match *self {
+            AnyColor::Black => &Black,
+            AnyColor::Red => &Red,
+            AnyColor::Green => &Green,
+            AnyColor::Yellow => &Yellow,
+            AnyColor::Blue => &Blue,
+            AnyColor::Magenta => &Magenta,
+            AnyColor::Cyan => &Cyan,
+            AnyColor::White => &White,
+            AnyColor::LightBlack => &LightBlack,
+            AnyColor::LightRed => &LightRed,
+            AnyColor::LightGreen => &LightGreen,
+            AnyColor::LightYellow => &LightYellow,
+            AnyColor::LightBlue => &LightBlue,
+            AnyColor::LightMagenta => &LightMagenta,
+            AnyColor::LightCyan => &LightCyan,
+            AnyColor::LightWhite => &LightWhite,
+            _ => return None,
+        })
+    }
+
+    /// Replace a named color by its ANSI value.
+    fn normalized(self) -> AnyColor {
+        match self.named_index() {
+            Some(n) => AnyColor::Ansi(AnsiValue(n)),
+            None => self,
+        }
+    }
+
+    /// Get the named color with the given ANSI value, from 0 to 15.
+    pub(crate) fn named(n: u8) -> AnyColor {
+        NAMED[n as usize]
+    }
+
+    /// The SGR parameters selecting this color as the foreground color.
+    pub(crate) fn fg_params(&self) -> String {
+        match *self {
+            AnyColor::Reset => "39".to_owned(),
+            AnyColor::Ansi(AnsiValue(n)) => format!("38;5;{}", n),
+            AnyColor::Rgb(Rgb(r, g, b)) => format!("38;2;{};{};{}", r, g, b),
+            _ => self.basic_params(30),
+        }
+    }
+
+    /// The SGR parameters selecting this color as the background color.
+    pub(crate) fn bg_params(&self) -> String {
+        match *self {
+            AnyColor::Reset => "49".to_owned(),
+            AnyColor::Ansi(AnsiValue(n)) => format!("48;5;{}", n),
+            AnyColor::Rgb(Rgb(r, g, b)) => format!("48;2;{};{};{}", r, g, b),
+            _ => self.basic_params(40),
+        }
+    }
+
+    /// Get the nearest color available with the given color depth.
+    ///
+    /// With `ColorDepth::Ansi16` and `ColorDepth::Ansi8`, this is a named color, and with
+    /// `ColorDepth::Monochrome`, this is always `AnyColor::Reset`.
+    pub fn downsample(self, depth: ColorDepth) -> AnyColor {
+        match (self, depth) {
+            (AnyColor::Reset, _) | (_, ColorDepth::TrueColor) => self,
+            (_, ColorDepth::Monochrome) => AnyColor::Reset,
+            (AnyColor::Rgb(c), ColorDepth::Ansi256) => AnyColor::Ansi(c.to_ansi256()),
+            (AnyColor::Rgb(c), ColorDepth::Ansi16) => c.nearest_basic(16),
+            (AnyColor::Rgb(c), _) => c.nearest_basic(8),
+            (AnyColor::Ansi(AnsiValue(n)), ColorDepth::Ansi8) if n < 8 => AnyColor::named(n),
+            // The bright colors are usually rendered as bold on 8-color terminals.
+            (AnyColor::Ansi(AnsiValue(n)), ColorDepth::Ansi8) if n < 16 => AnyColor::named(n - 8),
+            (AnyColor::Ansi(AnsiValue(n)), ColorDepth::Ansi16) if n < 16 => AnyColor::named(n),
+            (AnyColor::Ansi(_), ColorDepth::Ansi256) => self,
+            (AnyColor::Ansi(c), _) => AnyColor::Rgb(c.to_rgb()).downsample(depth),
+            (_, ColorDepth::Ansi8) => {
+                let n = self.named_index().unwrap_or(0);
+                if n < 8 { self } else { AnyColor::named(n - 8) }
+            }
+            // Named colors are available everywhere else.
+            (_, _) => self,
+        }
+    }
+
+    /// The SGR parameters selecting this color, using the basic codes (e.g. `31` for a red
+    /// foreground) for the 16 basic colors.
+    ///
+    /// `base` is 30 for the foreground, and 40 for the background.
+    fn basic_params(&self, base: u8) -> String {
+        match self.normalized() {
+            AnyColor::Ansi(AnsiValue(n)) if n < 8 => (base + n).to_string(),
+            AnyColor::Ansi(AnsiValue(n)) if n < 16 => (base + 60 + n - 8).to_string(),
+            _ if base == 30 => self.fg_params(),
+            _ => self.bg_params(),
+        }
+    }
+}
+
+impl From<Reset> for AnyColor {
+    fn from(_: Reset) -> AnyColor {
+        AnyColor::Reset
+    }
+}
+
+impl From<AnsiValue> for AnyColor {
+    fn from(color: AnsiValue) -> AnyColor {
+        AnyColor::Ansi(color)
+    }
+}
+
+impl From<Rgb> for AnyColor {
+    fn from(color: Rgb) -> AnyColor {
+        AnyColor::Rgb(color)
+    }
+}
+
+impl Color for AnyColor {
+    #[inline]
+    fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AnyColor::Reset => Reset.write_fg(f),
+            AnyColor::Ansi(c) => c.write_fg(f),
+            AnyColor::Rgb(c) => c.write_fg(f),
+            _ => self.as_named().map_or(Ok(()), |c| c.write_fg(f)),
+        }
+    }
+
+    #[inline]
+    fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AnyColor::Reset => Reset.write_bg(f),
+            AnyColor::Ansi(c) => c.write_bg(f),
+            AnyColor::Rgb(c) => c.write_bg(f),
+            _ => self.as_named().map_or(Ok(()), |c| c.write_bg(f)),
+        }
+    }
+
+    #[inline]
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AnyColor::Reset => Reset.write_underline(f),
+            AnyColor::Ansi(c) => c.write_underline(f),
+            AnyColor::Rgb(c) => c.write_underline(f),
+            _ => self.as_named().map_or(Ok(()), |c| c.write_underline(f)),
+        }
+    }
+}
+
+impl fmt::Display for AnyColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AnyColor::Reset => f.write_str("reset"),
+            AnyColor::Ansi(AnsiValue(n)) => write!(f, "ansi({})", n),
+            AnyColor::Rgb(Rgb(r, g, b)) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            _ => f.write_str(self.named_index().map_or("", |n| NAMES[n as usize])),
+        }
+    }
+}
+
+impl str::FromStr for AnyColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AnyColor, String> {
+        parse_color_spec(s).ok_or_else(|| format!("invalid color `{}`", s))
+    }
+}
+
+/// Parse a color, in any of the formats of `AnyColor`.
+fn parse_color_spec(spec: &str) -> Option<AnyColor> {
+    /// Parse the arguments of `name(...)`.
+    fn args<'a>(spec: &'a str, name: &str) -> Option<Vec<&'a str>> {
+        let spec = spec.strip_prefix(name)?.trim_start().strip_prefix('(')?.strip_suffix(')')?;
+        Some(spec.split(',').map(|arg| arg.trim()).collect())
+    }
+
+    let spec = spec.trim().to_ascii_lowercase();
+    let spec = &spec[..];
+
+    if spec == "reset" || spec == "default" {
+        return Some(AnyColor::Reset);
+    }
+
+    // Allow `bright-red`, `bright red`, `bright_red`, `brightred` and `light-red`.
+    let name = spec.replace(['-', '_', ' '], "").replacen("light", "bright", 1);
+    if let Some(i) = NAMES.iter().position(|n| n.replace('-', "") == name) {
+        return Some(NAMED[i]);
+    }
+
+    if let Some(hex) = spec.strip_prefix('#') {
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize, len: usize| {
+            u8::from_str_radix(hex.get(i * len..(i + 1) * len)?, 16).ok()
+        };
+        return match hex.len() {
+            3 => Some(AnyColor::Rgb(Rgb(digit(0, 1)? * 17, digit(1, 1)? * 17, digit(2, 1)? * 17))),
+            6 => Some(AnyColor::Rgb(Rgb(digit(0, 2)?, digit(1, 2)?, digit(2, 2)?))),
+            _ => None,
+        };
+    }
+
+    if spec.starts_with("rgb:") {
+        return parse_x11_rgb(spec).map(AnyColor::Rgb);
+    }
+
+    if let Some(args) = args(spec, "rgb") {
+        if args.len() != 3 {
+            return None;
+        }
+        let rgb = Rgb(decimal(args[0])?, decimal(args[1])?, decimal(args[2])?);
+        return Some(AnyColor::Rgb(rgb));
+    }
+
+    if let Some(args) = args(spec, "ansi") {
+        return match args[..] {
+            [n] => decimal(n).map(|n| AnyColor::Ansi(AnsiValue(n))),
+            _ => None,
+        };
+    }
+
+    if let Some(level) = spec.strip_prefix("grey").or_else(|| spec.strip_prefix("gray")) {
+        let level = decimal::<u32>(level).filter(|&level| level <= 100)?;
+        let level = ((level * 255 + 50) / 100) as u8;
+        return Some(AnyColor::Rgb(Rgb(level, level, level)));
+    }
+
+    decimal(spec).map(|n| AnyColor::Ansi(AnsiValue(n)))
+}
+
+/// Parse a decimal number, made of digits only (`str::parse` also accepts a leading `+`).
+fn decimal<T: str::FromStr>(s: &str) -> Option<T> {
+    if s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// When to write colors and styles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorChoice {
+    /// Follow the environment: colors are disabled if `NO_COLOR` is set, or if the standard
+    /// output is not a terminal, unless `CLICOLOR_FORCE` is set. `CLICOLOR=0` disables them too.
+    Auto,
+    /// Always write colors. This is the default.
+    Always,
+    /// Never write colors.
+    Never,
+}
+
+impl ColorChoice {
+    fn to_usize(self) -> usize {
+        match self {
+            ColorChoice::Always => 0,
+            ColorChoice::Auto => 1,
+            ColorChoice::Never => 2,
+        }
+    }
+
+    fn from_usize(n: usize) -> ColorChoice {
+        match n {
+            0 => ColorChoice::Always,
+            1 => ColorChoice::Auto,
+            _ => ColorChoice::Never,
+        }
+    }
+}
+
+impl str::FromStr for ColorChoice {
+    type Err = String;
+
+    /// Parse `auto`, `always` or `never`, as commonly used for a `--color` option.
+    fn from_str(s: &str) -> Result<ColorChoice, String> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("invalid color choice `{}` (expected auto, always or never)", s)),
+        }
+    }
+}
+
+static COLOR_CHOICE: AtomicUsize = AtomicUsize::new(0);
+static COLORS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Set when to write colors and styles, for the whole program.
+///
+/// With `ColorChoice::Auto`, the environment is checked right away, and not again later.
+///
+/// When colors are disabled, `Fg`, `Bg`, `UnderlineColor` and the sequences of the `style`
+/// module are displayed as nothing.
+pub fn set_color_choice(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => auto_colors(|name| env::var(name).ok(), is_tty(&io::stdout())),
+    };
+    COLOR_CHOICE.store(choice.to_usize(), Ordering::Relaxed);
+    COLORS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Get the current color choice.
+pub fn color_choice() -> ColorChoice {
+    ColorChoice::from_usize(COLOR_CHOICE.load(Ordering::Relaxed))
+}
+
+/// Are colors and styles currently written?
+pub fn colors_enabled() -> bool {
+    COLORS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Decide whether to use colors from the environment, as returned by `var`.
+fn auto_colors<F: Fn(&str) -> Option<String>>(var: F, is_tty: bool) -> bool {
+    let set = |name| var(name).is_some_and(|val| !val.is_empty());
+    let is = |name, value| var(name).as_ref().map(|val| &val[..]) == Some(value);
+
+    if set("NO_COLOR") {
+        false
+    } else if set("CLICOLOR_FORCE") && !is("CLICOLOR_FORCE", "0") {
+        true
+    } else if is("CLICOLOR", "0") {
+        false
+    } else {
+        is_tty && !is("TERM", "dumb")
+    }
 }
 
 /// A foreground color.
@@ -160,6 +1014,9 @@ pub struct Fg<C: Color>(pub C);
 
 impl<C: Color> fmt::Display for Fg<C> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
         self.0.write_fg(f)
     }
 }
@@ -170,22 +1027,355 @@ pub struct Bg<C: Color>(pub C);
 
 impl<C: Color> fmt::Display for Bg<C> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
         self.0.write_bg(f)
     }
 }
 
-/// Types that allow detection of the colors they support.
-pub trait DetectColors {
-    /// How many ANSI colors are supported (from 8 to 256)?
-    ///
-    /// Beware: the information given isn't authoritative, it's infered through escape codes or the
-    /// value of `TERM`, more colors may be available.
-    fn available_colors(&mut self) -> io::Result<u16>;
+/// An underline color.
+///
+/// This is only supported by some terminals (kitty, WezTerm, VTE, iTerm2 and mintty), and is
+/// mostly useful along with the extended underline styles, such as `style::CurlyUnderline`.
+#[derive(Debug, Clone, Copy)]
+pub struct UnderlineColor<C: Color>(pub C);
+
+impl<C: Color> fmt::Display for UnderlineColor<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !colors_enabled() {
+            return Ok(());
+        }
+        self.0.write_underline(f)
+    }
 }
 
-impl<W: Write> DetectColors for W {
-    fn available_colors(&mut self) -> io::Result<u16> {
-        let mut stdin = async_stdin();
+/// A special color of the terminal, which can be queried and changed with OSC sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TerminalColor {
+    /// The default foreground color (OSC 10).
+    Foreground,
+    /// The default background color (OSC 11).
+    Background,
+    /// The color of the text cursor (OSC 12).
+    Cursor,
+}
+
+impl TerminalColor {
+    fn code(self) -> u8 {
+        match self {
+            TerminalColor::Foreground => 10,
+            TerminalColor::Background => 11,
+            TerminalColor::Cursor => 12,
+        }
+    }
+}
+
+/// Change a special color of the terminal.
+#[derive(Debug, Clone, Copy)]
+pub struct SetTerminalColor(pub TerminalColor, pub Rgb);
+
+impl fmt::Display for SetTerminalColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Rgb(r, g, b) = self.1;
+        write!(f, osc!("{};rgb:{:02x}/{:02x}/{:02x}"), self.0.code(), r, g, b)
+    }
+}
+
+/// Reset a special color of the terminal to its configured value.
+#[derive(Debug, Clone, Copy)]
+pub struct ResetTerminalColor(pub TerminalColor);
+
+impl fmt::Display for ResetTerminalColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, osc!("{}"), 100 + self.0.code() as u16)
+    }
+}
+
+/// Types that allow querying the special colors of the terminal.
+///
+/// The replies of the terminal are read from `stdin`, which is usually an `async_stdin`, reused
+/// across queries.
+pub trait DetectTerminalColors {
+    /// Get the current value of a special color.
+    ///
+    /// This fails if the terminal doesn't answer.
+    fn terminal_color<R: Read>(&mut self, stdin: &mut R, color: TerminalColor) -> io::Result<Rgb>;
+
+    /// Is the background of the terminal dark?
+    ///
+    /// This queries the background color, falling back to the `COLORFGBG` environment variable
+    /// set by some terminals. This is useful to choose between a light and a dark theme.
+    fn is_background_dark<R: Read>(&mut self, stdin: &mut R) -> io::Result<bool>;
+
+    /// Get the current value of an entry of the 256-color palette.
+    ///
+    /// This fails if the terminal doesn't answer.
+    fn palette_color<R: Read>(&mut self, stdin: &mut R, index: u8) -> io::Result<Rgb>;
+}
+
+impl<W: Write> DetectTerminalColors for W {
+    fn terminal_color<R: Read>(&mut self, stdin: &mut R, color: TerminalColor) -> io::Result<Rgb> {
+        let query = format!(osc!("{};?"), color.code());
+        let reply = osc::query(self, stdin, &query)?;
+        str::from_utf8(&reply)
+            .ok()
+            .and_then(|reply| reply.split_once(';'))
+            .and_then(|(_, spec)| parse_x11_rgb(spec))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Invalid color reply."))
+    }
+
+    fn palette_color<R: Read>(&mut self, stdin: &mut R, index: u8) -> io::Result<Rgb> {
+        query_palette_color(self, stdin, index)
+    }
+
+    fn is_background_dark<R: Read>(&mut self, stdin: &mut R) -> io::Result<bool> {
+        match self.terminal_color(stdin, TerminalColor::Background) {
+            Ok(color) => Ok(is_dark(color)),
+            Err(err) => {
+                env::var("COLORFGBG").ok().and_then(|var| colorfgbg_is_dark(&var)).ok_or(err)
+            }
+        }
+    }
+}
+
+/// Change an entry of the 256-color palette.
+#[derive(Debug, Clone, Copy)]
+pub struct SetPaletteColor(pub u8, pub Rgb);
+
+impl fmt::Display for SetPaletteColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Rgb(r, g, b) = self.1;
+        write!(f, osc!("4;{};rgb:{:02x}/{:02x}/{:02x}"), self.0, r, g, b)
+    }
+}
+
+/// Reset an entry of the 256-color palette to its configured value.
+#[derive(Debug, Clone, Copy)]
+pub struct ResetPaletteColor(pub u8);
+
+impl fmt::Display for ResetPaletteColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, osc!("104;{}"), self.0)
+    }
+}
+
+/// Reset the whole 256-color palette to its configured values.
+#[derive(Debug, Clone, Copy)]
+pub struct ResetPalette;
+
+impl fmt::Display for ResetPalette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, osc!("104"))
+    }
+}
+
+/// A wrapper which changes entries of the palette, and resets them on drop.
+///
+/// This is useful to use a custom theme while the program runs.
+///
+/// Dropping never panics: if the output is gone (e.g. a closed pipe), the error is silently
+/// ignored. Use `finish` to find out whether resetting succeeded.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color::{self, CustomPalette, Rgb};
+/// use std::io::{Write, stdout};
+///
+/// fn main() {
+///     let mut stdout = CustomPalette::from(stdout());
+///     stdout.set(1, Rgb(0xdc, 0x32, 0x2f)).unwrap();
+///     writeln!(stdout, "{}Solarized red", color::Fg(color::Red)).unwrap();
+/// }
+/// ```
+pub struct CustomPalette<W: Write> {
+    /// The output target.
+    output: W,
+    /// The indices of the changed entries.
+    changed: Vec<u8>,
+}
+
+impl<W: Write> CustomPalette<W> {
+    /// Wrap the provided output. The palette is not changed until `set` is called.
+    pub fn from(output: W) -> Self {
+        CustomPalette {
+            output: output,
+            changed: Vec::new(),
+        }
+    }
+
+    /// Change an entry of the palette.
+    pub fn set(&mut self, index: u8, color: Rgb) -> io::Result<()> {
+        write!(self.output, "{}", SetPaletteColor(index, color))?;
+        if !self.changed.contains(&index) {
+            self.changed.push(index);
+        }
+        Ok(())
+    }
+
+    /// Reset the changed entries of the palette and flush the output.
+    ///
+    /// After a successful call, dropping this wrapper does not write anything anymore. On failure,
+    /// resetting is attempted again on drop.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.reset()?;
+        self.output.flush()
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        if !self.changed.is_empty() {
+            write!(self.output, "\x1B]104")?;
+            for index in &self.changed {
+                write!(self.output, ";{}", index)?;
+            }
+            write!(self.output, "\x1B\\")?;
+            self.changed.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for CustomPalette<W> {
+    fn drop(&mut self) {
+        let _ = self.reset();
+        let _ = self.output.flush();
+    }
+}
+
+impl<W: Write> ops::Deref for CustomPalette<W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        &self.output
+    }
+}
+
+impl<W: Write> ops::DerefMut for CustomPalette<W> {
+    fn deref_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+}
+
+impl<W: Write> Write for CustomPalette<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// Query an entry of the palette using OSC 4.
+fn query_palette_color<W: Write + ?Sized, R: Read + ?Sized>(stdout: &mut W,
+                                                            stdin: &mut R,
+                                                            index: u8)
+                                                            -> io::Result<Rgb> {
+    let reply = osc::query(stdout, stdin, &format!(osc!("4;{};?"), index))?;
+    str::from_utf8(&reply)
+        .ok()
+        .and_then(|reply| parse_palette_reply(reply, index))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Invalid palette reply."))
+}
+
+/// Parse a `4 ; index ; spec` reply.
+fn parse_palette_reply(reply: &str, index: u8) -> Option<Rgb> {
+    let mut parts = reply.splitn(3, ';');
+    if parts.next()? != "4" || parts.next()?.parse::<u8>().ok()? != index {
+        return None;
+    }
+    parse_x11_rgb(parts.next()?)
+}
+
+/// Parse an X11 color specification such as `rgb:RRRR/GGGG/BBBB`, with 1 to 4 hex digits per
+/// component.
+fn parse_x11_rgb(spec: &str) -> Option<Rgb> {
+    // The alpha component, reported by some terminals, is ignored.
+    let spec = spec.strip_prefix("rgb:").or_else(|| spec.strip_prefix("rgba:"))?;
+
+    let mut components = [0; 3];
+    let mut parts = spec.split('/');
+    for c in &mut components {
+        let part = parts.next()?;
+        if part.is_empty() || part.len() > 4 || !part.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let value = u32::from_str_radix(part, 16).ok()?;
+        let max = (1 << (4 * part.len())) - 1;
+        *c = ((value * 255 + max / 2) / max) as u8;
+    }
+
+    Some(Rgb(components[0], components[1], components[2]))
+}
+
+/// Is the color closer to black than to white, in perceived brightness?
+fn is_dark(Rgb(r, g, b): Rgb) -> bool {
+    299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b) < 1000 * 128
+}
+
+/// Check the background color in a `COLORFGBG` value, such as `15;0` or `15;default;0`.
+fn colorfgbg_is_dark(var: &str) -> Option<bool> {
+    match var.rsplit(';').next()?.parse::<u8>().ok()? {
+        0..=6 | 8 => Some(true),
+        _ => Some(false),
+    }
+}
+
+/// The number of colors supported by a terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorDepth {
+    /// No colors.
+    Monochrome,
+    /// The 8 basic colors.
+    Ansi8,
+    /// The 8 basic colors and their bright versions.
+    Ansi16,
+    /// The 256 colors of `AnsiValue`.
+    Ansi256,
+    /// Any `Rgb` color.
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Get the depth allowing the given number of colors, as reported by terminfo.
+    pub fn from_colors(colors: u32) -> ColorDepth {
+        match colors {
+            0..=7 => ColorDepth::Monochrome,
+            8..=15 => ColorDepth::Ansi8,
+            16..=255 => ColorDepth::Ansi16,
+            256..=0xFF_FFFF => ColorDepth::Ansi256,
+            _ => ColorDepth::TrueColor,
+        }
+    }
+}
+
+/// Types that allow detection of the colors they support.
+pub trait DetectColors {
+    /// How many ANSI colors are supported (from 8 to 256)?
+    ///
+    /// Beware: the information given isn't authoritative, it's infered through escape codes or the
+    /// value of `TERM`, more colors may be available.
+    fn available_colors(&mut self) -> io::Result<u16>;
+
+    /// Detect the color depth of the terminal, reading its replies from `stdin`.
+    ///
+    /// This is much faster than `available_colors`: `COLORTERM` and the terminfo entry of `TERM`
+    /// are checked first, and the terminal is only asked through XTGETTCAP if they don't already
+    /// report truecolor support. The query is followed by a device attributes request, which
+    /// every terminal answers, so there is no waiting for terminals that don't support it. Without
+    /// an answer, the `colors` of the terminfo entry are used.
+    ///
+    /// The queries are written to `self`, which must be the terminal, in raw mode: they would end
+    /// up in the output of a pipe or a file, and the replies would be echoed otherwise.
+    ///
+    /// Beware: the information given isn't authoritative.
+    fn color_depth<R: Read>(&mut self, stdin: &mut R) -> io::Result<ColorDepth>;
+}
+
+impl<W: Write> DetectColors for W {
+    fn available_colors(&mut self) -> io::Result<u16> {
+        let mut stdin = async_stdin();
 
         if detect_color(self, &mut stdin, 0)? {
             // OSC 4 is supported, detect how many colors there are.
@@ -216,27 +1406,742 @@ impl<W: Write> DetectColors for W {
                })
         }
     }
+
+    fn color_depth<R: Read>(&mut self, stdin: &mut R) -> io::Result<ColorDepth> {
+        let term = env::var("TERM").unwrap_or_default();
+        match env::var("COLORTERM") {
+            Ok(ref val) if val == "truecolor" || val == "24bit" => return Ok(ColorDepth::TrueColor),
+            _ => {}
+        }
+        if term == "dumb" {
+            return Ok(ColorDepth::Monochrome);
+        }
+
+        let info = Terminfo::load(&term);
+        if let Some(ref info) = info {
+            if info.flag("RGB") || info.number("RGB").is_some() || info.flag("Tc") {
+                return Ok(ColorDepth::TrueColor);
+            }
+        }
+
+        if let Some(depth) = query_color_depth(self, stdin)? {
+            return Ok(depth);
+        }
+
+        Ok(terminfo_depth(info.as_ref(), &term))
+    }
+}
+
+/// Get the color depth from the terminfo entry of `term`, if any.
+fn terminfo_depth(info: Option<&Terminfo>, term: &str) -> ColorDepth {
+    match info {
+        // An entry without `colors` is a monochrome terminal.
+        Some(info) => ColorDepth::from_colors(info.colors.unwrap_or(0) as u32),
+        None if term.contains("256color") => ColorDepth::Ansi256,
+        None => ColorDepth::Ansi8,
+    }
+}
+
+/// Ask the terminal for its `RGB`, `Tc` and `colors` capabilities using XTGETTCAP.
+///
+/// Returns `None` if the terminal doesn't know them.
+fn query_color_depth<W: Write + ?Sized, R: Read + ?Sized>(stdout: &mut W,
+                                                        stdin: &mut R)
+                                                        -> io::Result<Option<ColorDepth>> {
+    // `ESC P + q name ST`, with the name in hex, followed by `ESC [ c`.
+    for name in &["RGB", "Tc", "colors"] {
+        write!(stdout, "\x1BP+q")?;
+        for b in name.bytes() {
+            write!(stdout, "{:02X}", b)?;
+        }
+        write!(stdout, "\x1B\\")?;
+    }
+    write!(stdout, "\x1B[c")?;
+    stdout.flush()?;
+
+    // Either consume all data up to the device attributes or wait for a timeout.
+    let read_chars = osc::read_reply(stdin, ends_with_device_attributes)?;
+    Ok(parse_capabilities(&read_chars))
+}
+
+/// Does `buf` end with a `CSI ? params c` reply?
+fn ends_with_device_attributes(buf: &[u8]) -> bool {
+    if !buf.ends_with(b"c") {
+        return false;
+    }
+    match buf.windows(3).rposition(|w| w == b"\x1B[?") {
+        Some(beg) => buf[beg + 3..buf.len() - 1].iter().all(|&b| b == b';' || b.is_ascii_digit()),
+        None => false,
+    }
+}
+
+/// Parse the `ESC P 1 + r name = value ST` replies to XTGETTCAP.
+fn parse_capabilities(buf: &[u8]) -> Option<ColorDepth> {
+    fn unhex(hex: &str) -> Option<String> {
+        let bytes = (0..hex.len() / 2)
+            .map(|i| u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        String::from_utf8(bytes).ok()
+    }
+
+    let buf = String::from_utf8_lossy(buf);
+    let mut depth = None;
+    for reply in buf.split("\x1BP1+r").skip(1) {
+        let reply = match reply.find('\x1B') {
+            Some(end) => &reply[..end],
+            None => continue,
+        };
+        for cap in reply.split(';') {
+            let mut parts = cap.splitn(2, '=');
+            let name = parts.next().and_then(unhex);
+            let value = parts.next().and_then(unhex);
+            let found = match name.as_ref().map(|name| &name[..]) {
+                Some("RGB") | Some("Tc") => ColorDepth::TrueColor,
+                Some("colors") => {
+                    match value.and_then(|value| value.parse().ok()) {
+                        Some(colors) => ColorDepth::from_colors(colors),
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+            depth = depth.max(Some(found));
+        }
+    }
+    depth
+}
+
+/// A wrapper which rewrites the colors written to it, to suit a given color depth.
+///
+/// Every color of the SGR sequences going through it is replaced by the nearest available one.
+/// With `ColorDepth::Monochrome`, colors are removed altogether. Other output is left untouched.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color::{self, ColorAdapter, ColorDepth, Rgb};
+/// use std::io::{Write, stdout};
+///
+/// fn main() {
+///     let mut stdout = ColorAdapter::new(stdout(), ColorDepth::Ansi256);
+///     writeln!(stdout, "{}Orange", color::Fg(Rgb(255, 136, 0))).unwrap();
+/// }
+/// ```
+pub struct ColorAdapter<W: Write> {
+    /// The output target.
+    output: W,
+    /// The color depth to convert to.
+    depth: ColorDepth,
+    /// The beginning of an escape sequence, which is not complete yet.
+    pending: Vec<u8>,
+}
+
+impl<W: Write> ColorAdapter<W> {
+    /// Wrap the provided output, converting colors to the given depth.
+    pub fn new(output: W, depth: ColorDepth) -> ColorAdapter<W> {
+        ColorAdapter {
+            output: output,
+            depth: depth,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Wrap the provided output, converting colors to the detected depth of the terminal.
+    ///
+    /// The terminal is queried through `output`, which must be the terminal in raw mode (see
+    /// `DetectColors::color_depth`). Use `ColorAdapter::new` for other outputs.
+    pub fn detect<R: Read>(mut output: W, stdin: &mut R) -> io::Result<ColorAdapter<W>> {
+        let depth = output.color_depth(stdin)?;
+        Ok(ColorAdapter::new(output, depth))
+    }
+
+    /// Get the color depth colors are converted to.
+    pub fn depth(&self) -> ColorDepth {
+        self.depth
+    }
+
+    /// Rewrite the complete sequences of `data`, returning the index of the incomplete one.
+    fn rewrite(&self, data: &[u8], out: &mut Vec<u8>) -> usize {
+        /// Incomplete sequences longer than this are not sequences we care about.
+        const MAX_PENDING: usize = 64;
+
+        let mut i = 0;
+        while i < data.len() {
+            if data[i] != 0x1B {
+                let end = data[i..].iter().position(|&b| b == 0x1B).map_or(data.len(), |n| i + n);
+                out.extend_from_slice(&data[i..end]);
+                i = end;
+                continue;
+            }
+
+            match data.get(i + 1) {
+                Some(&b'[') => {}
+                Some(_) => {
+                    out.push(0x1B);
+                    i += 1;
+                    continue;
+                }
+                None => return i,
+            }
+
+            // Look for the final byte of the CSI sequence.
+            match data[i + 2..].iter().position(|b| (0x40..=0x7E).contains(b)) {
+                Some(n) => {
+                    let end = i + 2 + n;
+                    let params = &data[i + 2..end];
+                    let sgr = data[end] == b'm' &&
+                              params.iter().all(|&b| b.is_ascii_digit() || b == b';' || b == b':');
+                    if sgr {
+                        if let Some(params) = rewrite_sgr(params, self.depth) {
+                            out.extend_from_slice(b"\x1B[");
+                            out.extend_from_slice(params.as_bytes());
+                            out.push(b'm');
+                        }
+                    } else {
+                        out.extend_from_slice(&data[i..end + 1]);
+                    }
+                    i = end + 1;
+                }
+                None if data.len() - i > MAX_PENDING => {
+                    out.extend_from_slice(&data[i..]);
+                    i = data.len();
+                }
+                None => return i,
+            }
+        }
+        i
+    }
+}
+
+/// Rewrite the parameters of a SGR sequence, returning `None` if nothing is left.
+fn rewrite_sgr(params: &[u8], depth: ColorDepth) -> Option<String> {
+    let params = str::from_utf8(params).unwrap_or("");
+    if params.is_empty() {
+        return Some(String::new());
+    }
+
+    let list: Vec<&str> = params.split(';').collect();
+    let mut rewritten = Vec::new();
+    let mut i = 0;
+    while i < list.len() {
+        let param = list[i];
+        i += 1;
+
+        let (base, color) = if param.contains(':') {
+            // `38:5:n`, `38:2:r:g:b` or `38:2:colorspace:r:g:b`.
+            let sub: Vec<&str> = param.split(':').collect();
+            let base = sub[0];
+            let color = match sub.get(1) {
+                Some(&"5") => parse_color(&sub[2..], 5),
+                Some(&"2") if sub.len() > 5 => parse_color(&sub[3..], 2),
+                Some(&"2") => parse_color(&sub[2..], 2),
+                _ => None,
+            };
+            (base, color)
+        } else if param == "38" || param == "48" || param == "58" {
+            let color = match list.get(i) {
+                Some(&"5") => parse_color(&list[i + 1..], 5),
+                Some(&"2") => parse_color(&list[i + 1..], 2),
+                _ => None,
+            };
+            if color.is_some() {
+                i += if list[i] == "5" { 2 } else { 4 };
+            }
+            (param, color)
+        } else {
+            match param.parse::<u8>() {
+                Ok(n @ 30..=37) | Ok(n @ 40..=47) => {
+                    (if n < 40 { "38" } else { "48" }, Some(AnyColor::named(n % 10)))
+                }
+                Ok(n @ 90..=97) | Ok(n @ 100..=107) => {
+                    (if n < 100 { "38" } else { "48" }, Some(AnyColor::named(n % 10 + 8)))
+                }
+                _ => (param, None),
+            }
+        };
+
+        let color = match color {
+            Some(color) => color.downsample(depth),
+            None => {
+                rewritten.push(param.to_owned());
+                continue;
+            }
+        };
+
+        match (base, depth) {
+            (_, ColorDepth::Monochrome) => {}
+            ("38", ColorDepth::Ansi8) | ("38", ColorDepth::Ansi16) => {
+                rewritten.push(color.basic_params(30))
+            }
+            ("48", ColorDepth::Ansi8) | ("48", ColorDepth::Ansi16) => {
+                rewritten.push(color.basic_params(40))
+            }
+            ("38", _) => rewritten.push(color.fg_params()),
+            ("48", _) => rewritten.push(color.bg_params()),
+            // Underline colors have no basic codes.
+            (_, _) => rewritten.push(format!("5{}", &color.normalized().fg_params()[1..])),
+        }
+    }
+
+    if rewritten.is_empty() {
+        None
+    } else {
+        Some(rewritten.join(";"))
+    }
+}
+
+/// Parse the arguments of an extended color, `n` if `kind` is 5, or `r;g;b` if it is 2.
+fn parse_color(args: &[&str], kind: u8) -> Option<AnyColor> {
+    let mut args = args.iter().map(|arg| arg.parse::<u8>());
+    match kind {
+        5 => Some(AnyColor::Ansi(AnsiValue(args.next()?.ok()?))),
+        _ => Some(AnyColor::Rgb(Rgb(args.next()?.ok()?, args.next()?.ok()?, args.next()?.ok()?))),
+    }
+}
+
+impl<W: Write> ops::Deref for ColorAdapter<W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        &self.output
+    }
+}
+
+impl<W: Write> ops::DerefMut for ColorAdapter<W> {
+    fn deref_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+}
+
+impl<W: Write> Write for ColorAdapter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = mem::take(&mut self.pending);
+        data.extend_from_slice(buf);
+
+        let mut out = Vec::with_capacity(data.len());
+        let rest = self.rewrite(&data, &mut out);
+        self.output.write_all(&out)?;
+        self.pending = data.split_off(rest);
+        Ok(buf.len())
+    }
+
+    /// Flush the output.
+    ///
+    /// The beginning of an escape sequence which is not complete yet is written unchanged, as it
+    /// can't be rewritten.
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.write_all(&self.pending)?;
+        self.pending.clear();
+        self.output.flush()
+    }
+}
+
+impl<W: Write> Drop for ColorAdapter<W> {
+    fn drop(&mut self) {
+        let _ = self.output.write_all(&self.pending);
+    }
 }
 
 /// Detect a color using OSC 4.
-fn detect_color(stdout: &mut Write, stdin: &mut Read, color: u16) -> io::Result<bool> {
+fn detect_color(stdout: &mut dyn Write, stdin: &mut dyn Read, color: u16) -> io::Result<bool> {
     // Is the color available?
-    // Use `ESC ] 4 ; color ; ? BEL`.
-    write!(stdout, "\x1B]4;{};?\x07", color)?;
-    stdout.flush()?;
+    // Use `ESC ] 4 ; color ; ? ST`, and check that the reply is valid.
+    Ok(color <= 255 && query_palette_color(stdout, stdin, color as u8).is_ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_terminal_color() {
+        let mut out = Vec::new();
+        let mut stdin = &b"\x1B]10;rgb:ffff/ffff/ffff\x1B\\\x1B]11;rgb:0000/0000/0000\x07"[..];
+
+        assert_eq!(out.terminal_color(&mut stdin, TerminalColor::Foreground).unwrap(),
+                   Rgb(255, 255, 255));
+        assert!(out.is_background_dark(&mut stdin).unwrap());
+        assert_eq!(out, b"\x1B]10;?\x1B\\\x1B]11;?\x1B\\");
+    }
+
+    #[test]
+    fn test_palette_color() {
+        let mut out = Vec::new();
+        let mut stdin = &b"\x1B]4;1;rgb:cd/00/00\x07\x1B]4;2;rgb:00/cd/00\x07"[..];
+
+        assert_eq!(out.palette_color(&mut stdin, 1).unwrap(), Rgb(0xCD, 0, 0));
+        assert_eq!(out.palette_color(&mut stdin, 2).unwrap(), Rgb(0, 0xCD, 0));
+        assert!(out.palette_color(&mut stdin, 3).is_err());
+    }
+
+    #[test]
+    fn test_terminal_color_sequences() {
+        assert_eq!(SetTerminalColor(TerminalColor::Background, Rgb(0, 0x80, 0xFF)).to_string(),
+                   "\x1B]11;rgb:00/80/ff\x1B\\");
+        assert_eq!(ResetTerminalColor(TerminalColor::Cursor).to_string(), "\x1B]112\x1B\\");
+    }
+
+    #[test]
+    fn test_parse_x11_rgb() {
+        assert_eq!(parse_x11_rgb("rgb:ffff/8080/0000"), Some(Rgb(255, 128, 0)));
+        assert_eq!(parse_x11_rgb("rgb:f/8/0"), Some(Rgb(255, 136, 0)));
+        assert_eq!(parse_x11_rgb("rgb:1e1e/1e/2"), Some(Rgb(30, 30, 34)));
+        assert_eq!(parse_x11_rgb("rgba:0000/0000/0000/ffff"), Some(Rgb(0, 0, 0)));
+        assert_eq!(parse_x11_rgb("rgb:ffff/ffff"), None);
+        assert_eq!(parse_x11_rgb("rgb:fffff/0/0"), None);
+        assert_eq!(parse_x11_rgb("#ffffff"), None);
+    }
+
+    #[test]
+    fn test_palette() {
+        assert_eq!(SetPaletteColor(1, Rgb(0xdc, 0x32, 0x2f)).to_string(),
+                   "\x1B]4;1;rgb:dc/32/2f\x1B\\");
+        assert_eq!(ResetPaletteColor(1).to_string(), "\x1B]104;1\x1B\\");
+        assert_eq!(parse_palette_reply("4;1;rgb:dcdc/3232/2f2f", 1), Some(Rgb(0xdc, 0x32, 0x2f)));
+        assert_eq!(parse_palette_reply("4;2;rgb:dcdc/3232/2f2f", 1), None);
+        assert_eq!(parse_palette_reply("11;rgb:0/0/0", 1), None);
+
+        let mut palette = CustomPalette::from(Vec::new());
+        palette.set(1, Rgb(0, 0, 0)).unwrap();
+        palette.set(9, Rgb(0, 0, 0)).unwrap();
+        palette.set(1, Rgb(255, 0, 0)).unwrap();
+        palette.output.clear();
+        palette.finish().unwrap();
+        assert_eq!(&palette[..], b"\x1B]104;1;9\x1B\\");
+
+        // Nothing left to reset.
+        palette.output.clear();
+        drop(palette);
+    }
+
+    #[cfg(not(target_os = "redox"))]
+    #[test]
+    fn test_available_colors() {
+        use raw::IntoRawMode;
+
+        if !::test::is_pty_child() {
+            return ::test::run_in_pty("color::test::test_available_colors");
+        }
+        let mut out = io::stdout().into_raw_mode().unwrap();
+        assert_eq!(out.available_colors().unwrap(), 16);
+    }
+
+    #[test]
+    fn test_color_depth() {
+        assert_eq!(ColorDepth::from_colors(0), ColorDepth::Monochrome);
+        assert_eq!(ColorDepth::from_colors(8), ColorDepth::Ansi8);
+        assert_eq!(ColorDepth::from_colors(88), ColorDepth::Ansi16);
+        assert_eq!(ColorDepth::from_colors(256), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::from_colors(0x100_0000), ColorDepth::TrueColor);
+        assert!(ColorDepth::Ansi256 < ColorDepth::TrueColor);
+
+        let info = |colors| Terminfo { colors: colors, ..Terminfo::default() };
+        assert_eq!(terminfo_depth(Some(&info(Some(256))), "xterm-256color"),
+                   ColorDepth::Ansi256);
+        assert_eq!(terminfo_depth(Some(&info(Some(8))), "xterm-256color"), ColorDepth::Ansi8);
+        assert_eq!(terminfo_depth(Some(&info(None)), "vt100"), ColorDepth::Monochrome);
+        assert_eq!(terminfo_depth(None, "xterm-256color"), ColorDepth::Ansi256);
+        assert_eq!(terminfo_depth(None, "unknown"), ColorDepth::Ansi8);
+    }
+
+    #[test]
+    fn test_query_color_depth() {
+        let mut out = Vec::new();
+        let mut stdin = &b"\x1BP0+r\x1B\\\x1BP0+r\x1B\\\x1BP1+r636F6C6F7273=323536\x1B\\\x1B[?62c\
+                           \x1B]4;1;rgb:cd/00/00\x07"[..];
+
+        assert_eq!(query_color_depth(&mut out, &mut stdin).unwrap(), Some(ColorDepth::Ansi256));
+        // The reply to the next query is left intact.
+        assert_eq!(out.palette_color(&mut stdin, 1).unwrap(), Rgb(0xCD, 0, 0));
+    }
+
+    #[test]
+    fn test_parse_capabilities() {
+        assert!(ends_with_device_attributes(b"\x1B[?62;22c"));
+        assert!(ends_with_device_attributes(b"\x1BP0+r\x1B\\\x1B[?1;2c"));
+        assert!(!ends_with_device_attributes(b"\x1BP0+r\x1B\\"));
+        assert!(!ends_with_device_attributes(b"\x1B[?62;2"));
+
+        // Unknown capabilities.
+        assert_eq!(parse_capabilities(b"\x1BP0+r\x1B\\\x1BP0+r\x1B\\\x1B[?62c"), None);
+        assert_eq!(parse_capabilities(b"\x1B[?62c"), None);
+        // `colors=256`.
+        assert_eq!(parse_capabilities(b"\x1BP0+r\x1B\\\x1BP1+r636F6C6F7273=323536\x1B\\"),
+                   Some(ColorDepth::Ansi256));
+        // `RGB` and `colors=256`.
+        assert_eq!(parse_capabilities(b"\x1BP1+r524742=382F382F38\x1B\\\
+                                        \x1BP1+r636F6C6F7273=323536\x1B\\"),
+                   Some(ColorDepth::TrueColor));
+    }
+
+    #[test]
+    fn test_hsl_hsv() {
+        fn close(a: (f32, f32, f32), b: (f32, f32, f32)) -> bool {
+            (a.0 - b.0).abs() < 0.5 && (a.1 - b.1).abs() < 0.01 && (a.2 - b.2).abs() < 0.01
+        }
+
+        assert!(close(Rgb(255, 0, 0).to_hsl(), (0.0, 1.0, 0.5)));
+        assert!(close(Rgb(255, 136, 0).to_hsl(), (32.0, 1.0, 0.5)));
+        assert!(close(Rgb(128, 128, 128).to_hsl(), (0.0, 0.0, 0.5)));
+        assert!(close(Rgb(0, 0, 128).to_hsv(), (240.0, 1.0, 0.5)));
+        assert!(close(Rgb(255, 0, 255).to_hsv(), (300.0, 1.0, 1.0)));
+        assert_eq!(Rgb::from_hsl(120.0, 1.0, 0.25), Rgb(0, 128, 0));
+        assert_eq!(Rgb::from_hsv(-60.0, 1.0, 1.0), Rgb(255, 0, 255));
+
+        for &color in &[Rgb(0, 0, 0), Rgb(255, 255, 255), Rgb(12, 34, 56), Rgb(250, 10, 128)] {
+            let (h, s, l) = color.to_hsl();
+            assert_eq!(Rgb::from_hsl(h, s, l), color);
+            let (h, s, v) = color.to_hsv();
+            assert_eq!(Rgb::from_hsv(h, s, v), color);
+        }
+    }
 
-    let mut buf: [u8; 1] = [0];
-    let mut total_read = 0;
+    #[test]
+    fn test_interpolation() {
+        let (black, white) = (Rgb(0, 0, 0), Rgb(255, 255, 255));
+        assert_eq!(black.lerp(white, 0.5), Rgb(128, 128, 128));
+        assert_eq!(black.lerp(white, 2.0), white);
+        assert_eq!(Rgb(255, 0, 0).blend(Rgb(0, 0, 255), 0.25), Rgb(64, 0, 191));
 
-    let timeout = Duration::from_millis(CONTROL_SEQUENCE_TIMEOUT);
-    let now = SystemTime::now();
-    let bell = 7u8;
+        let (red, green) = (Rgb(255, 0, 0), Rgb(0, 255, 0));
+        assert_eq!(red.lerp_perceptual(green, 0.0), red);
+        assert_eq!(red.lerp_perceptual(green, 1.0), green);
+        // Perceptual interpolation avoids the muddy middle of linear interpolation.
+        let (h, s, l) = red.lerp_perceptual(green, 0.5).to_hsl();
+        assert!(h > 30.0 && h < 90.0 && s > 0.9 && l > 0.3, "{:?}", (h, s, l));
 
-    // Either consume all data up to bell or wait for a timeout.
-    while buf[0] != bell && now.elapsed().unwrap() < timeout {
-        total_read += stdin.read(&mut buf)?;
+        assert_eq!(Rgb(255, 0, 0).lighten(0.25), Rgb(255, 128, 128));
+        assert_eq!(Rgb(255, 0, 0).darken(0.25), Rgb(128, 0, 0));
+        assert_eq!(white.lighten(0.5), white);
     }
 
-    // If there was a response, the color is supported.
-    Ok(total_read > 0)
+    #[test]
+    fn test_gradient() {
+        let gradient = Gradient::new(&[Rgb(0, 0, 0), Rgb(255, 0, 0), Rgb(255, 255, 255)]);
+        let steps: Vec<Rgb> = gradient.steps(5).collect();
+        assert_eq!(steps.len(), 5);
+        assert_eq!(steps[0], Rgb(0, 0, 0));
+        assert_eq!(steps[2], Rgb(255, 0, 0));
+        assert_eq!(steps[4], Rgb(255, 255, 255));
+        assert_eq!(gradient.steps(1).collect::<Vec<_>>(), vec![Rgb(0, 0, 0)]);
+        assert_eq!(gradient.steps(0).len(), 0);
+
+        assert_eq!(Gradient::new(&[Rgb(1, 2, 3)]).at(0.5), Rgb(1, 2, 3));
+    }
+
+    #[test]
+    fn test_to_ansi() {
+        assert_eq!(AnsiValue(9).to_rgb(), Rgb(255, 0, 0));
+        assert_eq!(AnsiValue(208).to_rgb(), Rgb(255, 135, 0));
+        assert_eq!(AnsiValue(244).to_rgb(), Rgb(128, 128, 128));
+
+        for n in 16..=255 {
+            assert_eq!(AnsiValue(n).to_rgb().to_ansi256(), AnsiValue(n));
+        }
+        assert_eq!(Rgb(255, 136, 0).to_ansi256(), AnsiValue(208));
+        assert_eq!(Rgb(0x30, 0x30, 0x33).to_ansi256(), AnsiValue::grayscale(4));
+        assert_eq!(Rgb(255, 136, 0).to_ansi16(), AnyColor::Yellow);
+        assert_eq!(Rgb(250, 10, 20).to_ansi16(), AnyColor::LightRed);
+        assert_eq!(Rgb(10, 10, 10).to_ansi16(), AnyColor::Black);
+    }
+
+    #[test]
+    fn test_downsample() {
+        let orange = AnyColor::Rgb(Rgb(255, 136, 0));
+        assert_eq!(orange.downsample(ColorDepth::TrueColor), orange);
+        assert_eq!(orange.downsample(ColorDepth::Ansi256), AnyColor::Ansi(AnsiValue(208)));
+        assert_eq!(orange.downsample(ColorDepth::Ansi16), AnyColor::Yellow);
+        assert_eq!(orange.downsample(ColorDepth::Monochrome), AnyColor::Reset);
+        assert_eq!(AnyColor::Ansi(AnsiValue(196)).downsample(ColorDepth::Ansi16),
+                   AnyColor::LightRed);
+        assert_eq!(AnyColor::Ansi(AnsiValue(9)).downsample(ColorDepth::Ansi8), AnyColor::Red);
+        assert_eq!(AnyColor::Ansi(AnsiValue(4)).downsample(ColorDepth::Ansi16), AnyColor::Blue);
+        assert_eq!(AnyColor::LightCyan.downsample(ColorDepth::Ansi8), AnyColor::Cyan);
+
+        // The basic codes are used once downsampled.
+        assert_eq!(Fg(orange.downsample(ColorDepth::Ansi16)).to_string(), "\x1B[33m");
+        assert_eq!(Bg(orange.downsample(ColorDepth::Ansi8)).to_string(), "\x1B[43m");
+    }
+
+    #[test]
+    fn test_color_adapter() {
+        fn adapt(depth: ColorDepth, chunks: &[&str]) -> String {
+            let mut adapter = ColorAdapter::new(Vec::new(), depth);
+            for chunk in chunks {
+                adapter.write_all(chunk.as_bytes()).unwrap();
+            }
+            String::from_utf8(adapter.to_vec()).unwrap()
+        }
+
+        let text = "a\x1B[1;38;2;255;136;0;48;5;21mb\x1B[0m\x1B[2Jc";
+        assert_eq!(adapt(ColorDepth::TrueColor, &[text]), text);
+        assert_eq!(adapt(ColorDepth::Ansi256, &[text]),
+                   "a\x1B[1;38;5;208;48;5;21mb\x1B[0m\x1B[2Jc");
+        assert_eq!(adapt(ColorDepth::Ansi16, &[text]), "a\x1B[1;33;44mb\x1B[0m\x1B[2Jc");
+        assert_eq!(adapt(ColorDepth::Ansi8, &["\x1B[91;38:5:9m"]), "\x1B[31;31m");
+        assert_eq!(adapt(ColorDepth::Monochrome, &[text]), "a\x1B[1mb\x1B[0m\x1B[2Jc");
+        assert_eq!(adapt(ColorDepth::Monochrome, &["\x1B[31mx\x1B[m"]), "x\x1B[m");
+
+        // Sequences split across writes.
+        assert_eq!(adapt(ColorDepth::Ansi256, &["a\x1B", "[38;2;255", ";136;0m", "b"]),
+                   "a\x1B[38;5;208mb");
+        // Colon sub-parameters, and underline colors.
+        assert_eq!(adapt(ColorDepth::Ansi256, &["\x1B[38:2::255:136:0;58;2;255;136;0m"]),
+                   "\x1B[38;5;208;58;5;208m");
+        assert_eq!(adapt(ColorDepth::Ansi16, &["\x1B[58;2;255;136;0m"]), "\x1B[58;5;3m");
+        // Other sequences are left untouched.
+        assert_eq!(adapt(ColorDepth::Monochrome, &["\x1B[>4;1m\x1B[?25l\x1B]0;t\x07"]),
+                   "\x1B[>4;1m\x1B[?25l\x1B]0;t\x07");
+
+        // Incomplete sequences are written unchanged when flushing or dropping.
+        let mut adapter = ColorAdapter::new(Vec::new(), ColorDepth::Ansi256);
+        write!(adapter, "a\x1B").unwrap();
+        assert_eq!(&adapter[..], b"a");
+        adapter.flush().unwrap();
+        assert_eq!(&adapter[..], b"a\x1B");
+
+        let mut out = Vec::new();
+        write!(ColorAdapter::new(&mut out, ColorDepth::Ansi256), "b\x1B[38;2").unwrap();
+        assert_eq!(out, b"b\x1B[38;2");
+    }
+
+    #[test]
+    fn test_auto_colors() {
+        fn auto(vars: &[(&str, &str)], is_tty: bool) -> bool {
+            auto_colors(|name| {
+                            vars.iter().find(|&&(n, _)| n == name).map(|&(_, v)| v.to_owned())
+                        },
+                        is_tty)
+        }
+
+        assert!(auto(&[], true));
+        assert!(!auto(&[], false));
+        assert!(!auto(&[("NO_COLOR", "1")], true));
+        assert!(auto(&[("NO_COLOR", "")], true));
+        assert!(!auto(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")], true));
+        assert!(auto(&[("CLICOLOR_FORCE", "1")], false));
+        assert!(!auto(&[("CLICOLOR_FORCE", "0")], false));
+        assert!(!auto(&[("CLICOLOR", "0")], true));
+        assert!(auto(&[("CLICOLOR", "1")], true));
+        assert!(!auto(&[("TERM", "dumb")], true));
+
+        assert_eq!("never".parse(), Ok(ColorChoice::Never));
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+    }
+
+    #[test]
+    fn test_parse_color() {
+        let cases = [("reset", AnyColor::Reset),
+                     ("red", AnyColor::Red),
+                     ("bright-red", AnyColor::LightRed),
+                     ("bright-white", AnyColor::LightWhite),
+                     ("ansi(208)", AnyColor::Ansi(AnsiValue(208))),
+                     ("#ff8800", AnyColor::Rgb(Rgb(255, 136, 0)))];
+        for &(spec, color) in &cases {
+            assert_eq!(spec.parse(), Ok(color));
+            assert_eq!(color.to_string(), spec);
+        }
+
+        let orange = Ok(AnyColor::Rgb(Rgb(255, 136, 0)));
+        assert_eq!("rgb(255,136,0)".parse(), orange);
+        assert_eq!(" RGB( 255, 136 , 0 ) ".parse(), orange);
+        assert_eq!("#F80".parse(), orange);
+        assert_eq!("rgb:ffff/8888/0000".parse(), orange);
+        assert_eq!("Light_Blue".parse(), Ok(AnyColor::LightBlue));
+        assert_eq!("brightblack".parse(), Ok(AnyColor::LightBlack));
+        assert_eq!("default".parse(), Ok(AnyColor::Reset));
+        assert_eq!("208".parse(), Ok(AnyColor::Ansi(AnsiValue(208))));
+        assert_eq!("grey12".parse(), Ok(AnyColor::Rgb(Rgb(31, 31, 31))));
+        assert_eq!("gray100".parse(), Ok(AnyColor::Rgb(Rgb(255, 255, 255))));
+
+        for spec in &["", "redd", "#ff88", "#gg8800", "rgb(1,2)", "rgb(256,0,0)", "ansi(256)",
+                      "ansi()", "grey101", "rgb:ff/88", "#+f+f+f", "#+ff+88+00", "+5",
+                      "ansi(+5)", "rgb(+1,2,3)", "grey+50", "rgb:+f/+f/+f"] {
+            assert!(spec.parse::<AnyColor>().is_err(), "{:?} was parsed", spec);
+        }
+    }
+
+    #[test]
+    fn test_any_color_from() {
+        use std::collections::HashSet;
+
+        assert_eq!(AnyColor::from(Reset), AnyColor::Reset);
+        assert_eq!(AnyColor::from(LightMagenta), AnyColor::LightMagenta);
+        assert_eq!(AnyColor::from(AnsiValue(3)), AnyColor::Ansi(AnsiValue(3)));
+        assert_eq!(AnyColor::from(Rgb(1, 2, 3)), AnyColor::Rgb(Rgb(1, 2, 3)));
+
+        let colors: HashSet<AnyColor> =
+            vec![Red.into(), Red.into(), AnsiValue(1).into(), Rgb(205, 0, 0).into()]
+                .into_iter()
+                .collect();
+        assert_eq!(colors.len(), 3);
+    }
+
+    #[test]
+    fn test_named_any_color() {
+        assert_eq!(Fg(AnyColor::Red).to_string(), Fg(Red).to_string());
+        assert_eq!(Bg(AnyColor::LightCyan).to_string(), Bg(LightCyan).to_string());
+        assert_eq!(AnyColor::LightRed.fg_params(), "91");
+        assert_eq!(AnyColor::LightRed.bg_params(), "101");
+        assert_eq!(AnyColor::LightRed.downsample(ColorDepth::Ansi8), AnyColor::Red);
+        assert_eq!(AnyColor::Red.downsample(ColorDepth::Ansi256), AnyColor::Red);
+        assert_eq!(AnyColor::Red.downsample(ColorDepth::Monochrome), AnyColor::Reset);
+    }
+
+    #[test]
+    fn test_is_dark() {
+        assert!(is_dark(Rgb(0, 0, 0)));
+        assert!(is_dark(Rgb(0x28, 0x2c, 0x34)));
+        assert!(is_dark(Rgb(0, 0, 255)));
+        assert!(!is_dark(Rgb(255, 255, 255)));
+        assert!(!is_dark(Rgb(0xfd, 0xf6, 0xe3)));
+        assert!(!is_dark(Rgb(255, 255, 0)));
+
+        assert_eq!(colorfgbg_is_dark("15;0"), Some(true));
+        assert_eq!(colorfgbg_is_dark("0;default;15"), Some(false));
+        assert_eq!(colorfgbg_is_dark("default"), None);
+    }
+
+    #[test]
+    fn test_named_colors() {
+        assert_eq!(Fg(Black).to_string(), "\x1B[30m");
+        assert_eq!(Fg(White).to_string(), "\x1B[37m");
+        assert_eq!(Fg(LightBlack).to_string(), "\x1B[90m");
+        assert_eq!(Fg(LightWhite).to_string(), "\x1B[97m");
+        assert_eq!(Bg(Red).to_string(), "\x1B[41m");
+        assert_eq!(Bg(LightRed).to_string(), "\x1B[101m");
+        // The 256-color form is still available.
+        assert_eq!(Fg(AnsiValue(1)).to_string(), "\x1B[38;5;1m");
+        assert_eq!(Bg(AnsiValue(9)).to_string(), "\x1B[48;5;9m");
+    }
+
+    #[test]
+    fn test_underline_color() {
+        assert_eq!(UnderlineColor(Red).to_string(), "\x1B[58;5;1m");
+        assert_eq!(UnderlineColor(AnsiValue(200)).to_string(), "\x1B[58;5;200m");
+        assert_eq!(UnderlineColor(Rgb(1, 2, 3)).to_string(), "\x1B[58;2;1;2;3m");
+        assert_eq!(UnderlineColor(Reset).to_string(), "\x1B[59m");
+        assert_eq!(UnderlineColor(&Blue as &Color).to_string(), "\x1B[58;5;4m");
+    }
+
+    #[test]
+    fn test_default_underline_color() {
+        struct Basic(u8);
+
+        impl Color for Basic {
+            fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, csi!("{}m"), 30 + self.0)
+            }
+
+            fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, csi!("{}m"), 40 + self.0)
+            }
+        }
+
+        assert_eq!(UnderlineColor(Basic(2)).to_string(), "\x1B[58;5;2m");
+        assert_eq!(fg_to_underline("\x1B[38;2;1;2;3m"), Some("58;2;1;2;3".to_owned()));
+        assert_eq!(fg_to_underline("\x1B[95m"), Some("58;5;13".to_owned()));
+        assert_eq!(fg_to_underline("\x1B[39m"), Some("59".to_owned()));
+        assert_eq!(fg_to_underline("garbage"), None);
+    }
 }
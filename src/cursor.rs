@@ -1,10 +1,9 @@
 //! Cursor movement.
 
 use std::fmt;
-use std::io::{self, Write, Error, ErrorKind, Read};
+use std::io::{self, Write, Error, ErrorKind};
 use async::async_stdin;
-use std::time::{SystemTime, Duration};
-use raw::CONTROL_SEQUENCE_TIMEOUT;
+use osc;
 
 derive_csi_sequence!("Hide the cursor.", Hide, "?25l");
 derive_csi_sequence!("Show the cursor.", Show, "?25h");
@@ -101,19 +100,8 @@ impl<W: Write> DetectCursorPos for W {
         write!(self, "\x1B[6n")?;
         self.flush()?;
 
-        let mut buf: [u8; 1] = [0];
-        let mut read_chars = Vec::new();
-
-        let timeout = Duration::from_millis(CONTROL_SEQUENCE_TIMEOUT);
-        let now = SystemTime::now();
-
         // Either consume all data up to R or wait for a timeout.
-        while buf[0] != b'R' && now.elapsed().unwrap() < timeout {
-            if stdin.read(&mut buf)? > 0 {
-                read_chars.push(buf[0]);
-            }
-        }
-
+        let mut read_chars = osc::read_reply(&mut stdin, |buf| buf.ends_with(b"R"))?;
         if read_chars.len() == 0 {
             return Err(Error::new(ErrorKind::Other, "Cursor position detection timed out."));
         }
@@ -138,3 +126,20 @@ impl<W: Write> DetectCursorPos for W {
         Ok((cx, cy))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raw::IntoRawMode;
+    use std::io::stdout;
+
+    #[cfg(not(target_os = "redox"))]
+    #[test]
+    fn test_cursor_pos() {
+        if !::test::is_pty_child() {
+            return ::test::run_in_pty("cursor::test::test_cursor_pos");
+        }
+        let mut out = stdout().into_raw_mode().unwrap();
+        assert_eq!(out.cursor_pos().unwrap(), (10, 5));
+    }
+}
@@ -0,0 +1,142 @@
+//! Hyperlinks (OSC 8).
+//!
+//! Supporting terminals (e.g. VTE, iTerm2, kitty, WezTerm, Windows Terminal) make the text
+//! clickable, others just display it.
+//!
+//! # Example
+//!
+//! ```rust
+//! use termion::hyperlink::Hyperlink;
+//!
+//! fn main() {
+//!     println!("See {} for details.", Hyperlink::new("https://docs.rs/termion", "the docs"));
+//! }
+//! ```
+
+use std::fmt;
+use std::fmt::Write;
+
+/// Some content linking to an URI.
+///
+/// The URI and the id are percent-encoded where needed, so that they can't contain control
+/// characters (or anything else than printable ASCII) which would escape the sequence.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Hyperlink<T> {
+    uri: String,
+    id: Option<String>,
+    content: T,
+}
+
+impl<T: fmt::Display> Hyperlink<T> {
+    /// Create a link to `uri`, displaying `content`.
+    pub fn new(uri: &str, content: T) -> Hyperlink<T> {
+        Hyperlink {
+            uri: escape(uri, b""),
+            id: None,
+            content: content,
+        }
+    }
+
+    /// Set the id of the link.
+    ///
+    /// Terminals highlight all the parts of a link together when hovering over it, if they have
+    /// the same id and URI. This is useful for links broken over several lines, or links which
+    /// are partially overwritten.
+    pub fn id(mut self, id: &str) -> Hyperlink<T> {
+        // `:` and `;` separate the parameters, and `=` the keys from the values.
+        self.id = Some(escape(id, b":;="));
+        self
+    }
+
+    /// Get the (escaped) URI.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Get the start of the link, without the content and the end.
+    ///
+    /// This is useful for streaming the content, which must then be followed by `End`.
+    pub fn start(&self) -> Start<'_> {
+        Start {
+            uri: &self.uri,
+            id: self.id.as_ref().map(|id| &id[..]),
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Hyperlink<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.start(), self.content, End)
+    }
+}
+
+/// Start a hyperlink.
+///
+/// This is obtained through `Hyperlink::start`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Start<'a> {
+    uri: &'a str,
+    id: Option<&'a str>,
+}
+
+impl<'a> fmt::Display for Start<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.id {
+            Some(id) => write!(f, osc!("8;id={};{}"), id, self.uri),
+            None => write!(f, osc!("8;;{}"), self.uri),
+        }
+    }
+}
+
+/// End a hyperlink.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct End;
+
+impl fmt::Display for End {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, osc!("8;;"))
+    }
+}
+
+/// Percent-encode every byte which is not printable ASCII, along with the `extra` ones.
+fn escape(s: &str, extra: &[u8]) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        if b > 0x20 && b < 0x7F && !extra.contains(&b) {
+            escaped.push(b as char);
+        } else {
+            let _ = write!(escaped, "%{:02X}", b);
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use vt::VirtualTerminal;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Hyperlink::new("http://example.com", "text").to_string(),
+                   "\x1B]8;;http://example.com\x1B\\text\x1B]8;;\x1B\\");
+        assert_eq!(Hyperlink::new("http://example.com", 42).id("a").to_string(),
+                   "\x1B]8;id=a;http://example.com\x1B\\42\x1B]8;;\x1B\\");
+    }
+
+    #[test]
+    fn test_escape() {
+        let link = Hyperlink::new("http://x/\x1B\\\x07 é", "").id("a;b:c=d\n");
+        assert_eq!(link.uri(), "http://x/%1B\\%07%20%C3%A9");
+        assert_eq!(link.start().to_string(),
+                   "\x1B]8;id=a%3Bb%3Ac%3Dd%0A;http://x/%1B\\%07%20%C3%A9\x1B\\");
+    }
+
+    #[test]
+    fn test_invisible() {
+        let mut term = VirtualTerminal::new(20, 1);
+        write!(term, "a {} b", Hyperlink::new("file:///tmp", "link")).unwrap();
+        assert_eq!(term.contents(), "a link b");
+    }
+}
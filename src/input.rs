@@ -181,22 +181,55 @@ const EXIT_MOUSE_SEQUENCE: &'static str = csi!("?1006l\x1b[?1015l\x1b[?1002l\x1b
 
 /// A terminal with added mouse support.
 ///
-/// This can be obtained through the `From` implementations.
+/// This can be obtained through the `From` implementations, or through `try_from`.
+///
+/// Dropping never panics: if the output is gone (e.g. a closed pipe), the error is silently
+/// ignored. Use `finish` to find out whether disabling mouse support succeeded.
 pub struct MouseTerminal<W: Write> {
     term: W,
+    finished: bool,
 }
 
-impl<W: Write> From<W> for MouseTerminal<W> {
-    fn from(mut from: W) -> MouseTerminal<W> {
-        from.write_all(ENTER_MOUSE_SEQUENCE.as_bytes()).unwrap();
+impl<W: Write> MouseTerminal<W> {
+    /// Enable mouse support on the given output, returning an error if the escape sequence could
+    /// not be written.
+    pub fn try_from(mut from: W) -> io::Result<MouseTerminal<W>> {
+        from.write_all(ENTER_MOUSE_SEQUENCE.as_bytes())?;
 
-        MouseTerminal { term: from }
+        Ok(MouseTerminal {
+            term: from,
+            finished: false,
+        })
+    }
+
+    /// Disable mouse support and flush the output.
+    ///
+    /// Once the escape sequence is written, dropping this wrapper does not write anything anymore,
+    /// even if flushing fails. If writing fails, disabling is attempted again on drop.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.term.write_all(EXIT_MOUSE_SEQUENCE.as_bytes())?;
+        self.finished = true;
+        self.term.flush()
+    }
+}
+
+impl<W: Write> From<W> for MouseTerminal<W> {
+    /// Enable mouse support on the given output.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the escape sequence could not be written. See `MouseTerminal::try_from` for
+    /// a fallible version.
+    fn from(from: W) -> MouseTerminal<W> {
+        MouseTerminal::try_from(from).expect("enable mouse support")
     }
 }
 
 impl<W: Write> Drop for MouseTerminal<W> {
     fn drop(&mut self) {
-        self.term.write_all(EXIT_MOUSE_SEQUENCE.as_bytes()).unwrap();
+        if !self.finished {
+            let _ = self.term.write_all(EXIT_MOUSE_SEQUENCE.as_bytes());
+        }
     }
 }
 
@@ -356,6 +389,9 @@ mod test {
 
     #[test]
     fn test_read() {
+        if !::test::in_tty("input::test::test_read") {
+            return;
+        }
         let test1 = "this is the first test";
         let test2 = "this is the second test";
 
@@ -365,6 +401,9 @@ mod test {
 
     #[test]
     fn test_backspace() {
+        if !::test::in_tty("input::test::test_backspace") {
+            return;
+        }
         line_match("this is the\x7f first\x7f\x7f test",
                    Some("this is th fir test"));
         line_match("this is the seco\x7fnd test\x7f",
@@ -373,14 +412,64 @@ mod test {
 
     #[test]
     fn test_end() {
+        if !::test::in_tty("input::test::test_end") {
+            return;
+        }
         line_match("abc\nhttps://www.youtube.com/watch?v=dQw4w9WgXcQ",
                    Some("abc"));
         line_match("hello\rhttps://www.youtube.com/watch?v=yPYZpwSpKmA",
                    Some("hello"));
     }
 
+    #[test]
+    fn test_mouse_terminal_finish() {
+        let mut term = MouseTerminal::try_from(Vec::new()).unwrap();
+        term.finish().unwrap();
+        assert_eq!(&term[..],
+                   [ENTER_MOUSE_SEQUENCE, EXIT_MOUSE_SEQUENCE].concat().as_bytes());
+        drop(term);
+    }
+
+    #[test]
+    fn test_mouse_terminal_failed_flush() {
+        struct NoFlush<'a>(&'a mut Vec<u8>);
+
+        impl<'a> Write for NoFlush<'a> {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))
+            }
+        }
+
+        let mut buf = Vec::new();
+        {
+            let mut term = MouseTerminal::try_from(NoFlush(&mut buf)).unwrap();
+            assert!(term.finish().is_err());
+        }
+        // The exit sequence is only written once.
+        assert_eq!(buf, [ENTER_MOUSE_SEQUENCE, EXIT_MOUSE_SEQUENCE].concat().as_bytes());
+    }
+
+    #[test]
+    fn test_mouse_terminal_broken_output() {
+        let mut buf = [0u8; 4];
+        assert!(MouseTerminal::try_from(&mut buf[..]).is_err());
+
+        let mut buf = [0u8; 32];
+        let mut term = MouseTerminal::try_from(&mut buf[..]).unwrap();
+        assert!(term.finish().is_err());
+        // Must not panic.
+        drop(term);
+    }
+
     #[test]
     fn test_abort() {
+        if !::test::in_tty("input::test::test_abort") {
+            return;
+        }
         line_match("abc\x03https://www.youtube.com/watch?v=dQw4w9WgXcQ", None);
         line_match("hello\x04https://www.youtube.com/watch?v=yPYZpwSpKmA", None);
     }
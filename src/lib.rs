@@ -19,43 +19,227 @@ mod sys;
 #[path="sys/unix/mod.rs"]
 mod sys;
 
-pub use sys::size::terminal_size;
-pub use sys::tty::{is_tty, get_tty};
+pub use sys::size::{terminal_size, terminal_size_pixels, set_terminal_size};
+pub use sys::tty::{is_tty, get_tty, ttyname, is_foreground, controlling_tty};
+
+#[cfg(not(target_os = "redox"))]
+pub use sys::pty;
 
 mod async;
 pub use async::{AsyncReader, async_stdin};
 
 #[macro_use]
 mod macros;
+mod osc;
+mod terminfo;
+pub mod buffer;
 pub mod clear;
+pub mod clipboard;
 pub mod color;
 pub mod cursor;
 pub mod event;
+pub mod hyperlink;
 pub mod input;
+pub mod notification;
 pub mod raw;
 pub mod screen;
 pub mod scroll;
+pub mod size;
 pub mod style;
+pub mod title;
+pub mod vt;
 
 #[cfg(test)]
 mod test {
     use super::sys;
+    use std::{env, io};
+
+    /// Set in the child processes started by `run_in_pty`.
+    const PTY_CHILD: &'static str = "TERMION_TEST_PTY_CHILD";
+    /// Set in the child process started by `test_controlling_tty_not_found`.
+    const NO_TTY_CHILD: &'static str = "TERMION_TEST_NO_TTY_CHILD";
+
+    /// Is this process attached to a terminal, as the tests needing one expect?
+    fn has_tty() -> bool {
+        sys::tty::is_tty(&io::stdin()) && sys::tty::is_tty(&io::stdout()) &&
+        sys::tty::get_tty().is_ok()
+    }
+
+    /// Is this process a child started by `run_in_pty`?
+    pub fn is_pty_child() -> bool {
+        env::var_os(PTY_CHILD).is_some()
+    }
+
+    /// Should the test `name`, which needs a terminal, run in this process?
+    ///
+    /// Without a terminal (e.g. on CI), the test is run in a child attached to a pseudo-terminal
+    /// instead, and this returns `false`.
+    pub fn in_tty(name: &str) -> bool {
+        if has_tty() || is_pty_child() {
+            return true;
+        }
+        run_in_pty(name);
+        false
+    }
+
+    /// Run the test `name` in a child process attached to a pseudo-terminal, and check that it
+    /// passes.
+    ///
+    /// This process plays the terminal: the cursor is always at column 10 of row 5, and only the
+    /// first 16 palette entries exist.
+    #[cfg(not(target_os = "redox"))]
+    pub fn run_in_pty(name: &str) {
+        use std::io::{Read, Write};
+        use std::process::Command;
+        use sys::pty::Pty;
+
+        let mut pty = Pty::open().unwrap();
+        pty.resize(80, 24).unwrap();
+
+        let mut command = Command::new(env::current_exe().unwrap());
+        command.args(&[name, "--exact", "--nocapture", "--test-threads=1"]).env(PTY_CHILD, "1");
+        let mut child = pty.spawn(command).unwrap();
+
+        let mut output = Vec::new();
+        let mut buf = [0; 1024];
+        let mut pos = 0;
+        // Reading fails once the child and its slave descriptors are gone.
+        while let Ok(n) = pty.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..n]);
+
+            while let Some((end, reply)) = answer(&output[pos..]) {
+                pos += end;
+                pty.write_all(reply.as_bytes()).unwrap();
+            }
+        }
+
+        let status = child.wait().unwrap();
+        assert!(status.success(),
+                "{} failed in a pseudo-terminal:\n{}",
+                name,
+                String::from_utf8_lossy(&output));
+    }
+
+    #[cfg(target_os = "redox")]
+    pub fn run_in_pty(name: &str) {
+        panic!("{} needs a terminal.", name);
+    }
+
+    /// Find the first complete query in `output`.
+    ///
+    /// Returns where it ends, and the reply (empty for unknown sequences).
+    fn answer(output: &[u8]) -> Option<(usize, String)> {
+        let beg = output.iter().position(|&b| b == b'\x1B')?;
+        let seq = &output[beg..];
+
+        if seq.starts_with(b"\x1B[") {
+            let len = seq[2..].iter().position(|&b| b >= 0x40 && b <= 0x7E)? + 3;
+            let reply = if &seq[..len] == b"\x1B[6n" { "\x1B[5;10R" } else { "" };
+            Some((beg + len, reply.to_owned()))
+        } else if seq.starts_with(b"\x1B]") {
+            let len = seq.windows(2).position(|w| w == b"\x1B\\")?;
+            let query = String::from_utf8_lossy(&seq[2..len]);
+            let reply = match query.strip_prefix("4;").and_then(|q| q.strip_suffix(";?")) {
+                Some(index) if index.parse::<u8>().ok()? < 16 => {
+                    format!("\x1B]4;{};rgb:0000/0000/0000\x1B\\", index)
+                }
+                _ => String::new(),
+            };
+            Some((beg + len + 2, reply))
+        } else {
+            Some((beg + 1, String::new()))
+        }
+    }
 
     #[test]
     fn test_get_terminal_attr() {
-        sys::attr::get_terminal_attr().unwrap();
-        sys::attr::get_terminal_attr().unwrap();
-        sys::attr::get_terminal_attr().unwrap();
+        if !in_tty("test::test_get_terminal_attr") {
+            return;
+        }
+        sys::attr::get_terminal_attr(0).unwrap();
+        sys::attr::get_terminal_attr(0).unwrap();
+        sys::attr::get_terminal_attr(0).unwrap();
     }
 
     #[test]
     fn test_set_terminal_attr() {
-        let ios = sys::attr::get_terminal_attr().unwrap();
-        sys::attr::set_terminal_attr(&ios).unwrap();
+        if !in_tty("test::test_set_terminal_attr") {
+            return;
+        }
+        let ios = sys::attr::get_terminal_attr(0).unwrap();
+        sys::attr::set_terminal_attr(0, &ios).unwrap();
+    }
+
+    #[test]
+    fn test_ttyname() {
+        if !in_tty("test::test_ttyname") {
+            return;
+        }
+        let file = ::std::fs::File::open("Cargo.toml").unwrap();
+        assert!(sys::tty::ttyname(&file).is_err());
+
+        let tty = sys::tty::get_tty().unwrap();
+        assert!(sys::tty::ttyname(&tty).is_ok());
+    }
+
+    #[cfg(not(target_os = "redox"))]
+    #[test]
+    fn test_controlling_tty() {
+        if !is_pty_child() {
+            return run_in_pty("test::test_controlling_tty");
+        }
+        assert!(sys::tty::is_foreground().unwrap());
+        assert_eq!(sys::tty::controlling_tty().unwrap(),
+                   sys::tty::ttyname(&io::stdin()).unwrap());
+    }
+
+    #[cfg(not(target_os = "redox"))]
+    #[test]
+    fn test_controlling_tty_not_found() {
+        use std::process::{Command, Stdio};
+
+        if env::var_os(NO_TTY_CHILD).is_none() {
+            // Run in a child whose standard streams are not terminals.
+            let output = Command::new(env::current_exe().unwrap())
+                .args(&["test::test_controlling_tty_not_found", "--exact", "--test-threads=1"])
+                .env(NO_TTY_CHILD, "1")
+                .stdin(Stdio::null())
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+            return;
+        }
+        assert_eq!(sys::tty::controlling_tty().unwrap_err().kind(), io::ErrorKind::NotFound);
     }
 
     #[test]
     fn test_size() {
+        if !in_tty("test::test_size") {
+            return;
+        }
         sys::size::terminal_size().unwrap();
     }
+
+    #[test]
+    fn test_size_pixels() {
+        if !in_tty("test::test_size_pixels") {
+            return;
+        }
+        sys::size::terminal_size_pixels().unwrap();
+    }
+
+    #[test]
+    fn test_answer() {
+        assert_eq!(answer(b"ab\x1B[6n"), Some((6, "\x1B[5;10R".to_owned())));
+        assert_eq!(answer(b"\x1B[1;2H"), Some((6, String::new())));
+        assert_eq!(answer(b"\x1B]4;3;?\x1B\\"),
+                   Some((9, "\x1B]4;3;rgb:0000/0000/0000\x1B\\".to_owned())));
+        assert_eq!(answer(b"\x1B]4;16;?\x1B\\"), Some((10, String::new())));
+        assert_eq!(answer(b"\x1B]4;3;?"), None);
+        assert_eq!(answer(b"\x1B[1;2"), None);
+        assert_eq!(answer(b"abc"), None);
+    }
 }
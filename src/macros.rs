@@ -17,3 +17,8 @@ macro_rules! derive_csi_sequence {
         }
     };
 }
+
+/// Create an OSC-introduced sequence, terminated by ST.
+macro_rules! osc {
+    ($( $l:expr ),*) => { concat!("\x1B]", $( $l ),*, "\x1B\\") };
+}
@@ -0,0 +1,182 @@
+//! Desktop notifications.
+//!
+//! There is no standard escape sequence for notifications, so the protocol is chosen based on the
+//! terminal, as detected through environment variables. Terminals which support none of them get
+//! a plain bell, which usually makes them flash or mark the window as urgent.
+//!
+//! # Example
+//!
+//! ```rust
+//! use termion::notification::Notification;
+//!
+//! fn main() {
+//!     print!("{}", Notification::new("Build finished", "All 42 tests passed."));
+//! }
+//! ```
+
+use std::env;
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use osc::Spaced;
+
+/// A notification protocol.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    /// `OSC 9 ; message ST`, supported by iTerm2, ConEmu, WezTerm and Ghostty.
+    ///
+    /// This has no separate title.
+    Osc9,
+    /// `OSC 777 ; notify ; title ; body ST`, supported by VTE-based terminals, urxvt (with the
+    /// `notify` extension), foot, WezTerm and Ghostty.
+    Osc777,
+    /// The OSC 99 protocol of kitty.
+    Kitty,
+    /// A plain `BEL` character.
+    Bell,
+}
+
+impl Protocol {
+    /// Detect the protocol supported by the current terminal.
+    pub fn detect() -> Protocol {
+        detect_from(|name| env::var(name).ok())
+    }
+}
+
+/// Detect the protocol from the environment, as returned by `var`.
+fn detect_from<F: Fn(&str) -> Option<String>>(var: F) -> Protocol {
+    let term = var("TERM").unwrap_or_default();
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+
+    if var("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" {
+        Protocol::Kitty
+    } else if program == "iTerm.app" || program == "WezTerm" || program == "ghostty" ||
+              var("ConEmuPID").is_some() {
+        Protocol::Osc9
+    } else if var("VTE_VERSION").is_some() || term.starts_with("rxvt-unicode") ||
+              term.starts_with("foot") {
+        Protocol::Osc777
+    } else {
+        Protocol::Bell
+    }
+}
+
+/// The identifier of the next notification, used to join the chunks of the kitty protocol.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// A desktop notification, with a title and a body.
+///
+/// Control characters are replaced with spaces in both.
+#[derive(Copy, Clone, Debug)]
+pub struct Notification<'a> {
+    title: &'a str,
+    body: &'a str,
+    protocol: Protocol,
+    id: usize,
+}
+
+impl<'a> Notification<'a> {
+    /// Create a notification, using the detected protocol.
+    pub fn new(title: &'a str, body: &'a str) -> Notification<'a> {
+        Notification {
+            title: title,
+            body: body,
+            protocol: Protocol::detect(),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Use the given protocol instead of the detected one.
+    pub fn protocol(mut self, protocol: Protocol) -> Notification<'a> {
+        self.protocol = protocol;
+        self
+    }
+}
+
+impl<'a> fmt::Display for Notification<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.protocol {
+            Protocol::Osc9 => {
+                let message = if self.title.is_empty() {
+                    self.body.to_owned()
+                } else {
+                    format!("{}: {}", self.title, self.body)
+                };
+                write!(f, "\x1B]9;")?;
+                // ConEmu and Windows Terminal read `OSC 9 ; n ; ...` as the sub-command `n`.
+                if is_sub_command(&message) {
+                    f.write_str(" ")?;
+                }
+                write!(Spaced(f), "{}", message)?;
+                write!(f, "\x1B\\")
+            }
+            Protocol::Osc777 => {
+                // The title can't contain the separator.
+                write!(f, "\x1B]777;notify;")?;
+                write!(Spaced(f), "{};{}", self.title.replace(';', ","), self.body)?;
+                write!(f, "\x1B\\")
+            }
+            Protocol::Kitty => {
+                // The chunks are joined through their identifier, and the notification is only
+                // shown once complete (`d=1`, the default).
+                write!(f, "\x1B]99;i={}:d=0;", self.id)?;
+                write!(Spaced(f), "{}", self.title)?;
+                write!(f, "\x1B\\\x1B]99;i={}:p=body;", self.id)?;
+                write!(Spaced(f), "{}", self.body)?;
+                write!(f, "\x1B\\")
+            }
+            Protocol::Bell => write!(f, "\x07"),
+        }
+    }
+}
+
+/// Does `message` start with digits followed by `;`?
+fn is_sub_command(message: &str) -> bool {
+    let digits = message.bytes().take_while(u8::is_ascii_digit).count();
+    digits > 0 && message[digits..].starts_with(';')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        fn detect(vars: &[(&str, &str)]) -> Protocol {
+            detect_from(|name| {
+                vars.iter().find(|&&(n, _)| n == name).map(|&(_, value)| value.to_owned())
+            })
+        }
+
+        assert_eq!(detect(&[("TERM", "xterm-kitty")]), Protocol::Kitty);
+        assert_eq!(detect(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "iTerm.app")]),
+                   Protocol::Osc9);
+        assert_eq!(detect(&[("TERM", "xterm-256color"), ("VTE_VERSION", "7600")]),
+                   Protocol::Osc777);
+        assert_eq!(detect(&[("TERM", "rxvt-unicode-256color")]), Protocol::Osc777);
+        assert_eq!(detect(&[("TERM", "xterm")]), Protocol::Bell);
+        assert_eq!(detect(&[]), Protocol::Bell);
+    }
+
+    #[test]
+    fn test_display() {
+        let notification = Notification::new("Done; ok", "Took\n3s; 0 errors");
+        assert_eq!(notification.protocol(Protocol::Osc9).to_string(),
+                   "\x1B]9;Done; ok: Took 3s; 0 errors\x1B\\");
+        assert_eq!(notification.protocol(Protocol::Osc777).to_string(),
+                   "\x1B]777;notify;Done, ok;Took 3s; 0 errors\x1B\\");
+        assert_eq!(notification.protocol(Protocol::Kitty).to_string(),
+                   format!("\x1B]99;i={0}:d=0;Done; ok\x1B\\\x1B]99;i={0}:p=body;Took 3s; 0 \
+                            errors\x1B\\",
+                           notification.id));
+        assert!(Notification::new("", "").id != notification.id);
+        assert_eq!(notification.protocol(Protocol::Bell).to_string(), "\x07");
+        assert_eq!(Notification::new("", "a").protocol(Protocol::Osc9).to_string(),
+                   "\x1B]9;a\x1B\\");
+        assert_eq!(Notification::new("", "4;3 files left").protocol(Protocol::Osc9).to_string(),
+                   "\x1B]9; 4;3 files left\x1B\\");
+        assert_eq!(Notification::new("42;", "x").protocol(Protocol::Osc9).to_string(),
+                   "\x1B]9; 42;: x\x1B\\");
+        assert_eq!(Notification::new("", "42").protocol(Protocol::Osc9).to_string(),
+                   "\x1B]9;42\x1B\\");
+    }
+}
@@ -0,0 +1,124 @@
+//! Helpers for OSC sequences.
+
+use std::fmt::{self, Write as FmtWrite};
+use std::io::{self, Error, ErrorKind, Read, Write};
+use std::time::{SystemTime, Duration};
+use raw::CONTROL_SEQUENCE_TIMEOUT;
+
+/// Send an OSC query, and read the reply.
+///
+/// The returned reply is the part between `ESC ]` and the terminator (`BEL` or `ST`). Replies
+/// can be long (e.g. clipboard contents), so the timeout restarts whenever some data arrives.
+///
+/// `stdin` should be the same for successive queries (see `async_stdin`).
+pub fn query<W: Write + ?Sized, R: Read + ?Sized>(out: &mut W,
+                                                  stdin: &mut R,
+                                                  query: &str)
+                                                  -> io::Result<Vec<u8>> {
+    out.write_all(query.as_bytes())?;
+    out.flush()?;
+
+    let read_chars = read_reply(stdin, is_terminated)?;
+    if read_chars.is_empty() {
+        return Err(Error::new(ErrorKind::Other, "OSC query timed out."));
+    }
+
+    parse_reply(&read_chars)
+        .map(|reply| reply.to_vec())
+        .ok_or_else(|| Error::new(ErrorKind::Other, "Invalid OSC reply."))
+}
+
+/// Read the reply of the terminal to a query, until `terminated` returns true for the bytes read
+/// so far, or until a timeout.
+///
+/// The timeout restarts whenever some data arrives, so long replies are read entirely. An empty
+/// reply means that the terminal didn't answer.
+pub fn read_reply<R, F>(stdin: &mut R, terminated: F) -> io::Result<Vec<u8>>
+    where R: Read + ?Sized,
+          F: Fn(&[u8]) -> bool
+{
+    let mut buf: [u8; 1] = [0];
+    let mut read_chars = Vec::new();
+
+    let timeout = Duration::from_millis(CONTROL_SEQUENCE_TIMEOUT);
+    let mut now = SystemTime::now();
+
+    // Either consume all data up to the terminator or wait for a timeout.
+    while !terminated(&read_chars) && now.elapsed().unwrap() < timeout {
+        if stdin.read(&mut buf)? > 0 {
+            read_chars.push(buf[0]);
+            now = SystemTime::now();
+        }
+    }
+
+    Ok(read_chars)
+}
+
+fn is_terminated(buf: &[u8]) -> bool {
+    buf.ends_with(b"\x07") || buf.ends_with(b"\x1B\\")
+}
+
+/// Extract the content of the last OSC sequence of `buf`.
+fn parse_reply(buf: &[u8]) -> Option<&[u8]> {
+    let beg = buf.windows(2).rposition(|w| w == b"\x1B]")? + 2;
+    let buf = &buf[beg..];
+
+    if buf.ends_with(b"\x07") {
+        Some(&buf[..buf.len() - 1])
+    } else if buf.ends_with(b"\x1B\\") {
+        Some(&buf[..buf.len() - 2])
+    } else {
+        None
+    }
+}
+
+/// Formatter output which drops control characters, so that they can't end the sequence early.
+pub struct Sanitized<'a, 'b: 'a>(pub &'a mut fmt::Formatter<'b>);
+
+impl<'a, 'b> FmtWrite for Sanitized<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for part in s.split(char::is_control) {
+            self.0.write_str(part)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formatter output which replaces control characters with spaces, for text in which they
+/// separate words (e.g. line breaks).
+pub struct Spaced<'a, 'b: 'a>(pub &'a mut fmt::Formatter<'b>);
+
+impl<'a, 'b> FmtWrite for Spaced<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, part) in s.split(char::is_control).enumerate() {
+            if i > 0 {
+                self.0.write_char(' ')?;
+            }
+            self.0.write_str(part)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_reply() {
+        let mut stdin = &b"\x1B[5;10Rrest"[..];
+        assert_eq!(read_reply(&mut stdin, |buf| buf.ends_with(b"R")).unwrap(), b"\x1B[5;10R");
+        assert_eq!(stdin, b"rest");
+
+        let mut stdin = &b""[..];
+        assert!(read_reply(&mut stdin, |buf| buf.ends_with(b"R")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply(b"\x1B]52;c;YQ==\x07"), Some(&b"52;c;YQ=="[..]));
+        assert_eq!(parse_reply(b"junk\x1B]11;rgb:0/0/0\x1B\\"), Some(&b"11;rgb:0/0/0"[..]));
+        assert_eq!(parse_reply(b"\x1B]11;rgb:0/0/0"), None);
+        assert_eq!(parse_reply(b"\x1B[0n"), None);
+    }
+}
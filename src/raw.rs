@@ -24,6 +24,7 @@
 
 use std::io::{self, Write};
 use std::ops;
+use std::os::unix::io::{AsRawFd, RawFd};
 
 use sys::Termios;
 use sys::attr::{get_terminal_attr, raw_terminal_attr, set_terminal_attr};
@@ -35,14 +36,63 @@ pub const CONTROL_SEQUENCE_TIMEOUT: u64 = 100;
 /// dropped.
 ///
 /// Restoring will entirely bring back the old TTY state.
+///
+/// Dropping never panics: if the terminal has gone away (e.g. the session was disconnected), the
+/// error is silently ignored. Use `restore` to find out whether restoring succeeded.
 pub struct RawTerminal<W: Write> {
     prev_ios: Termios,
+    /// The terminal whose state is changed.
+    fd: RawFd,
+    restored: bool,
     output: W,
 }
 
+impl<W: Write + AsRawFd> RawTerminal<W> {
+    /// Switch the terminal `output` refers to to raw mode.
+    ///
+    /// Unlike `into_raw_mode`, which changes the terminal standard input is attached to, this
+    /// changes the given terminal. This is useful with `/dev/tty` when standard input is
+    /// redirected, or with the slave side of a pseudo-terminal.
+    pub fn from_tty(output: W) -> io::Result<RawTerminal<W>> {
+        RawTerminal::new(output.as_raw_fd(), output)
+    }
+}
+
+impl<W: Write> RawTerminal<W> {
+    /// Switch the terminal `fd` refers to to raw mode, and wrap `output`.
+    fn new(fd: RawFd, output: W) -> io::Result<RawTerminal<W>> {
+        let mut ios = get_terminal_attr(fd)?;
+        let prev_ios = ios;
+
+        raw_terminal_attr(&mut ios);
+
+        set_terminal_attr(fd, &ios)?;
+
+        Ok(RawTerminal {
+            prev_ios: prev_ios,
+            fd: fd,
+            restored: false,
+            output: output,
+        })
+    }
+
+    /// Restore the previous state of the terminal.
+    ///
+    /// After a successful call, the terminal is no longer in raw mode and dropping this wrapper
+    /// does not touch the terminal anymore. On failure, the restoration is attempted again on
+    /// drop.
+    pub fn restore(&mut self) -> io::Result<()> {
+        set_terminal_attr(self.fd, &self.prev_ios)?;
+        self.restored = true;
+        Ok(())
+    }
+}
+
 impl<W: Write> Drop for RawTerminal<W> {
     fn drop(&mut self) {
-        set_terminal_attr(&self.prev_ios).unwrap();
+        if !self.restored {
+            let _ = set_terminal_attr(self.fd, &self.prev_ios);
+        }
     }
 }
 
@@ -82,22 +132,15 @@ pub trait IntoRawMode: Write + Sized {
     /// Raw mode means that stdin won't be printed (it will instead have to be written manually by
     /// the program). Furthermore, the input isn't canonicalised or buffered (that is, you can
     /// read from stdin one byte of a time). The output is neither modified in any way.
+    ///
+    /// This changes the terminal standard input is attached to. See `RawTerminal::from_tty` to
+    /// change another one.
     fn into_raw_mode(self) -> io::Result<RawTerminal<Self>>;
 }
 
 impl<W: Write> IntoRawMode for W {
     fn into_raw_mode(self) -> io::Result<RawTerminal<W>> {
-        let mut ios = get_terminal_attr()?;
-        let prev_ios = ios;
-
-        raw_terminal_attr(&mut ios);
-
-        set_terminal_attr(&ios)?;
-
-        Ok(RawTerminal {
-            prev_ios: prev_ios,
-            output: self,
-        })
+        RawTerminal::new(0, self)
     }
 }
 
@@ -108,10 +151,45 @@ mod test {
 
     #[test]
     fn test_into_raw_mode() {
+        if !::test::in_tty("raw::test::test_into_raw_mode") {
+            return;
+        }
+        let prev_ios = get_terminal_attr(0).unwrap();
         let mut out = stdout().into_raw_mode().unwrap();
+        assert!(get_terminal_attr(0).unwrap().c_lflag != prev_ios.c_lflag);
 
         out.write_all(b"this is a test, muahhahahah\r\n").unwrap();
 
         drop(out);
+        assert_eq!(get_terminal_attr(0).unwrap().c_lflag, prev_ios.c_lflag);
+    }
+
+    #[test]
+    fn test_restore() {
+        if !::test::in_tty("raw::test::test_restore") {
+            return;
+        }
+        let mut out = stdout().into_raw_mode().unwrap();
+
+        out.restore().unwrap();
+        out.write_all(b"this is a test, back to normal\n").unwrap();
+    }
+
+    #[cfg(not(target_os = "redox"))]
+    #[test]
+    fn test_from_tty() {
+        use pty::Pty;
+
+        let pty = Pty::open().unwrap();
+        let slave = pty.open_slave().unwrap();
+        let prev_ios = get_terminal_attr(slave.as_raw_fd()).unwrap();
+
+        let mut out = RawTerminal::from_tty(slave.try_clone().unwrap()).unwrap();
+        let ios = get_terminal_attr(slave.as_raw_fd()).unwrap();
+        assert!(ios.c_lflag != prev_ios.c_lflag);
+
+        out.restore().unwrap();
+        let ios = get_terminal_attr(slave.as_raw_fd()).unwrap();
+        assert_eq!(ios.c_lflag, prev_ios.c_lflag);
     }
 }
@@ -46,23 +46,54 @@ impl fmt::Display for ToAlternateScreen {
 ///
 /// This is achieved by switching the terminal to the alternate screen on creation and
 /// automatically switching it back to the original screen on drop.
+///
+/// Dropping never panics: if the output is gone (e.g. a closed pipe), the error is silently
+/// ignored. Use `finish` to find out whether switching back succeeded.
 pub struct AlternateScreen<W: Write> {
     /// The output target.
     output: W,
+    /// Whether the terminal was already switched back to the main screen.
+    finished: bool,
 }
 
 impl<W: Write> AlternateScreen<W> {
     /// Create an alternate screen wrapper struct for the provided output and switch the terminal
     /// to the alternate screen.
-    pub fn from(mut output: W) -> Self {
-        write!(output, "{}", ToAlternateScreen).expect("switch to alternate screen");
-        AlternateScreen { output: output }
+    ///
+    /// # Panics
+    ///
+    /// This panics if switching to the alternate screen fails. See `try_from` for a fallible
+    /// version.
+    pub fn from(output: W) -> Self {
+        AlternateScreen::try_from(output).expect("switch to alternate screen")
+    }
+
+    /// Create an alternate screen wrapper struct for the provided output and switch the terminal
+    /// to the alternate screen, returning an error if the escape sequence could not be written.
+    pub fn try_from(mut output: W) -> io::Result<Self> {
+        write!(output, "{}", ToAlternateScreen)?;
+        Ok(AlternateScreen {
+            output: output,
+            finished: false,
+        })
+    }
+
+    /// Switch the terminal back to the main screen and flush the output.
+    ///
+    /// Once the escape sequence is written, dropping this wrapper does not write anything anymore,
+    /// even if flushing fails. If writing fails, switching back is attempted again on drop.
+    pub fn finish(&mut self) -> io::Result<()> {
+        write!(self.output, "{}", ToMainScreen)?;
+        self.finished = true;
+        self.output.flush()
     }
 }
 
 impl<W: Write> Drop for AlternateScreen<W> {
     fn drop(&mut self) {
-        write!(self, "{}", ToMainScreen).expect("switch to main screen");
+        if !self.finished {
+            let _ = write!(self.output, "{}", ToMainScreen);
+        }
     }
 }
 
@@ -89,3 +120,43 @@ impl<W: Write> Write for AlternateScreen<W> {
         self.output.flush()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io;
+
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))
+        }
+    }
+
+    #[test]
+    fn test_finish() {
+        let mut screen = AlternateScreen::try_from(Vec::new()).unwrap();
+        write!(screen, "x").unwrap();
+        screen.finish().unwrap();
+        assert_eq!(&screen[..], b"\x1B[?1049hx\x1B[?1049l");
+        drop(screen);
+    }
+
+    #[test]
+    fn test_broken_output() {
+        assert!(AlternateScreen::try_from(Broken).is_err());
+
+        let mut screen = AlternateScreen {
+            output: Broken,
+            finished: false,
+        };
+        assert!(screen.finish().is_err());
+        // Must not panic.
+        drop(screen);
+    }
+}
@@ -0,0 +1,213 @@
+//! Terminal size detection.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use termion::async_stdin;
+//! use termion::raw::IntoRawMode;
+//! use termion::size::{self, DetectPixelSize};
+//! use std::io::stdout;
+//!
+//! fn main() {
+//!     let ((cols, rows), source) = size::detect_terminal_size().unwrap();
+//!     println!("The terminal has {}x{} cells ({:?}).", cols, rows, source);
+//!
+//!     let mut stdout = stdout().into_raw_mode().unwrap();
+//!     let (width, height) = stdout.cell_size(&mut async_stdin()).unwrap();
+//!     println!("Each cell is {}x{} pixels.", width, height);
+//! }
+//! ```
+
+use std::env;
+use std::io::{self, Write, Error, ErrorKind, Read};
+use std::str;
+use cursor::{self, DetectCursorPos};
+use osc;
+use raw::RawTerminal;
+use sys::size::{terminal_size, terminal_size_of, terminal_size_pixels};
+use sys::tty::get_tty;
+
+/// Where the terminal size was obtained from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SizeSource {
+    /// The terminal standard output is attached to.
+    Stdout,
+    /// The terminal standard error is attached to.
+    Stderr,
+    /// The terminal standard input is attached to.
+    Stdin,
+    /// The controlling terminal, `/dev/tty`.
+    Tty,
+    /// The `COLUMNS` and `LINES` environment variables.
+    Env,
+    /// Moving the cursor to the bottom right corner and asking the terminal for its position.
+    CursorProbe,
+}
+
+/// Get the size of the terminal, even when standard output is not attached to it.
+///
+/// The following sources are tried in order, and the first one giving a non-zero size is used:
+///
+/// 1. Standard output, standard error, standard input and `/dev/tty`.
+/// 2. The `COLUMNS` and `LINES` environment variables.
+/// 3. Moving the cursor to the bottom right corner of `/dev/tty` and asking for its position. This
+///    briefly switches the terminal to raw mode, and restores the cursor afterwards.
+///
+/// Returns the size as `(columns, rows)` along with the source it was obtained from.
+pub fn detect_terminal_size() -> io::Result<((u16, u16), SizeSource)> {
+    let candidates = [(terminal_size_of(&io::stdout()), SizeSource::Stdout),
+                      (terminal_size_of(&io::stderr()), SizeSource::Stderr),
+                      (terminal_size_of(&io::stdin()), SizeSource::Stdin),
+                      (get_tty().and_then(|tty| terminal_size_of(&tty)), SizeSource::Tty)];
+    for &(ref size, source) in candidates.iter() {
+        if let Ok((cols, rows)) = *size {
+            if cols > 0 && rows > 0 {
+                return Ok(((cols, rows), source));
+            }
+        }
+    }
+
+    if let Some(size) = size_from_env(env::var("COLUMNS").ok(), env::var("LINES").ok()) {
+        return Ok((size, SizeSource::Env));
+    }
+
+    probe_size().map(|size| (size, SizeSource::CursorProbe))
+}
+
+/// Parse the `COLUMNS` and `LINES` environment variables.
+fn size_from_env(cols: Option<String>, rows: Option<String>) -> Option<(u16, u16)> {
+    let cols = cols?.trim().parse().ok()?;
+    let rows = rows?.trim().parse().ok()?;
+
+    if cols > 0 && rows > 0 {
+        Some((cols, rows))
+    } else {
+        None
+    }
+}
+
+/// Find the size of the terminal by moving the cursor as far as possible.
+fn probe_size() -> io::Result<(u16, u16)> {
+    let mut tty = RawTerminal::from_tty(get_tty()?)?;
+
+    write!(tty, "{}{}", cursor::Save, cursor::Goto(9999, 9999))?;
+    let pos = tty.cursor_pos();
+    write!(tty, "{}", cursor::Restore)?;
+    tty.flush()?;
+
+    pos
+}
+
+/// Types that allow detection of the terminal size in pixels.
+///
+/// The replies of the terminal are read from `stdin`, which is usually an `async_stdin`, reused
+/// across queries.
+pub trait DetectPixelSize {
+    /// Get the size of the terminal in pixels, as `(width, height)`.
+    ///
+    /// The kernel is asked first. If it reports zeros, the terminal itself is queried using
+    /// `CSI 14 t`.
+    fn terminal_size_pixels<R: Read>(&mut self, stdin: &mut R) -> io::Result<(u16, u16)>;
+
+    /// Get the size of a single character cell in pixels, as `(width, height)`.
+    ///
+    /// If the kernel knows the pixel size, this is derived from it and the size in cells.
+    /// Otherwise, the terminal is queried using `CSI 16 t`, falling back to dividing the answers
+    /// to `CSI 14 t` (the size in pixels) and `CSI 18 t` (the size in cells).
+    fn cell_size<R: Read>(&mut self, stdin: &mut R) -> io::Result<(u16, u16)>;
+}
+
+impl<W: Write> DetectPixelSize for W {
+    fn terminal_size_pixels<R: Read>(&mut self, stdin: &mut R) -> io::Result<(u16, u16)> {
+        match terminal_size_pixels() {
+            Ok((w, h)) if w > 0 && h > 0 => Ok((w, h)),
+            _ => query_window_op(self, stdin, 14, 4),
+        }
+    }
+
+    fn cell_size<R: Read>(&mut self, stdin: &mut R) -> io::Result<(u16, u16)> {
+        if let (Ok((cols, rows)), Ok((w, h))) = (terminal_size(), terminal_size_pixels()) {
+            if cols > 0 && rows > 0 && w > 0 && h > 0 {
+                return Ok((w / cols, h / rows));
+            }
+        }
+
+        if let Ok(size) = query_window_op(self, stdin, 16, 6) {
+            return Ok(size);
+        }
+        let (w, h) = query_window_op(self, stdin, 14, 4)?;
+        let (cols, rows) = query_window_op(self, stdin, 18, 8)?;
+        if cols == 0 || rows == 0 {
+            return Err(Error::new(ErrorKind::Other, "Terminal has no cells."));
+        }
+        Ok((w / cols, h / rows))
+    }
+}
+
+/// Send the XTWINOPS query `CSI op t` and read the `CSI reply ; height ; width t` answer.
+fn query_window_op<W: Write, R: Read>(out: &mut W,
+                                      stdin: &mut R,
+                                      op: u8,
+                                      reply: u8)
+                                      -> io::Result<(u16, u16)> {
+    write!(out, "\x1B[{}t", op)?;
+    out.flush()?;
+
+    // Either consume all data up to t or wait for a timeout.
+    let read_chars = osc::read_reply(stdin, |buf| buf.ends_with(b"t"))?;
+    if read_chars.is_empty() {
+        return Err(Error::new(ErrorKind::Other, "Window size query timed out."));
+    }
+
+    parse_window_op_reply(&read_chars, reply)
+        .ok_or_else(|| Error::new(ErrorKind::Other, "Invalid window size reply."))
+}
+
+/// Parse a `CSI reply ; height ; width t` answer into `(width, height)`.
+fn parse_window_op_reply(buf: &[u8], reply: u8) -> Option<(u16, u16)> {
+    let buf = str::from_utf8(buf).ok()?;
+    let beg = buf.rfind("\x1B[")?;
+    let mut nums = buf[beg + 2..].trim_end_matches('t').split(';');
+
+    if nums.next()?.parse::<u8>().ok()? != reply {
+        return None;
+    }
+    let height = nums.next()?.parse().ok()?;
+    let width = nums.next()?.parse().ok()?;
+
+    Some((width, height))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_window_op_reply() {
+        assert_eq!(parse_window_op_reply(b"\x1B[4;600;800t", 4), Some((800, 600)));
+        assert_eq!(parse_window_op_reply(b"garbage\x1B[6;16;8t", 6), Some((8, 16)));
+        assert_eq!(parse_window_op_reply(b"\x1B[4;600;800t", 6), None);
+        assert_eq!(parse_window_op_reply(b"\x1B[4;600t", 4), None);
+        assert_eq!(parse_window_op_reply(b"", 4), None);
+    }
+
+    #[test]
+    fn test_query_window_op() {
+        let mut out = Vec::new();
+        let mut stdin = &b"\x1B[6;16;8t\x1B[4;600;800t"[..];
+        assert_eq!(query_window_op(&mut out, &mut stdin, 16, 6).unwrap(), (8, 16));
+        assert_eq!(query_window_op(&mut out, &mut stdin, 14, 4).unwrap(), (800, 600));
+        assert_eq!(out, b"\x1B[16t\x1B[14t");
+    }
+
+    #[test]
+    fn test_size_from_env() {
+        let var = |s: &str| Some(s.to_owned());
+
+        assert_eq!(size_from_env(var("80"), var("24")), Some((80, 24)));
+        assert_eq!(size_from_env(var(" 132\n"), var("50")), Some((132, 50)));
+        assert_eq!(size_from_env(var("80"), None), None);
+        assert_eq!(size_from_env(var("0"), var("24")), None);
+        assert_eq!(size_from_env(var("wide"), var("24")), None);
+    }
+}
@@ -1,22 +1,546 @@
 //! Text styling management.
+//!
+//! Support for the various attributes differs between terminals. Bold, underlined and inverted
+//! text work everywhere, the others are documented with the terminals known to support them.
+//! Unsupported attributes are simply ignored by the terminal.
 
+use std::cell::Cell;
 use std::fmt;
+use std::ops;
 
-derive_csi_sequence!("Reset SGR parameters.", Reset, "m");
-derive_csi_sequence!("Bold text.", Bold, "1m");
-derive_csi_sequence!("Fainted text (not widely supported).", Faint, "2m");
-derive_csi_sequence!("Italic text.", Italic, "3m");
-derive_csi_sequence!("Underlined text.", Underline, "4m");
-derive_csi_sequence!("Blinking text (not widely supported).", Blink, "5m");
-derive_csi_sequence!("Inverted colors (negative mode).", Invert, "7m");
-derive_csi_sequence!("Crossed out text (not widely supported).", CrossedOut, "9m");
-derive_csi_sequence!("Undo bold text.", NoBold, "21m");
-derive_csi_sequence!("Undo fainted text (not widely supported).", NoFaint, "22m");
-derive_csi_sequence!("Undo italic text.", NoItalic, "23m");
-derive_csi_sequence!("Undo underlined text.", NoUnderline, "24m");
-derive_csi_sequence!("Undo blinking text (not widely supported).", NoBlink, "25m");
-derive_csi_sequence!("Undo inverted colors (negative mode).", NoInvert, "27m");
-derive_csi_sequence!("Undo crossed out text (not widely supported).",
-                     NoCrossedOut,
-                     "29m");
-derive_csi_sequence!("Framed text (not widely supported).", Framed, "51m");
+use color::{self, AnyColor};
+
+/// Derive a SGR sequence struct, which is displayed as nothing when colors are disabled.
+macro_rules! derive_sgr_sequence {
+    ($doc:expr, $name:ident, $value:expr) => {
+        #[doc = $doc]
+        #[derive(Copy, Clone)]
+        pub struct $name;
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                if !color::colors_enabled() {
+                    return Ok(());
+                }
+                write!(f, csi!($value))
+            }
+        }
+    };
+}
+
+derive_sgr_sequence!("Reset SGR parameters.", Reset, "m");
+derive_sgr_sequence!("Bold text.", Bold, "1m");
+derive_sgr_sequence!("Fainted text (not supported by the Linux console and Terminal.app).",
+                     Faint,
+                     "2m");
+derive_sgr_sequence!("Italic text (not supported by the Linux console).", Italic, "3m");
+derive_sgr_sequence!("Underlined text.", Underline, "4m");
+derive_sgr_sequence!("Double underlined text (supported by xterm, VTE, kitty, WezTerm and \
+                      iTerm2).",
+                     DoubleUnderline,
+                     "21m");
+derive_sgr_sequence!("Blinking text (not supported by VTE, kitty and Terminal.app).",
+                     Blink,
+                     "5m");
+derive_sgr_sequence!("Rapidly blinking text (rarely supported, usually rendered as slow \
+                      blinking).",
+                     RapidBlink,
+                     "6m");
+derive_sgr_sequence!("Inverted colors (negative mode).", Invert, "7m");
+derive_sgr_sequence!("Hidden text, e.g. for passwords (not supported by the Linux console).",
+                     Hidden,
+                     "8m");
+derive_sgr_sequence!("Crossed out text (not supported by the Linux console and Terminal.app).",
+                     CrossedOut,
+                     "9m");
+derive_sgr_sequence!("Framed text (supported by mintty and WezTerm only).", Framed, "51m");
+derive_sgr_sequence!("Encircled text (supported by mintty only).", Encircled, "52m");
+derive_sgr_sequence!("Overlined text (supported by xterm, VTE, kitty, WezTerm and mintty).",
+                     Overline,
+                     "53m");
+derive_sgr_sequence!("Superscript text (supported by mintty only).", Superscript, "73m");
+derive_sgr_sequence!("Subscript text (supported by mintty only).", Subscript, "74m");
+derive_sgr_sequence!("Curly underlined text (supported by kitty, WezTerm, VTE, iTerm2 and \
+                      mintty).",
+                     CurlyUnderline,
+                     "4:3m");
+derive_sgr_sequence!("Dotted underlined text (supported by kitty, WezTerm, VTE and mintty).",
+                     DottedUnderline,
+                     "4:4m");
+derive_sgr_sequence!("Dashed underlined text (supported by kitty, WezTerm, VTE and mintty).",
+                     DashedUnderline,
+                     "4:5m");
+derive_sgr_sequence!("Undo bold text.\n\nNote that this also undoes fainted text: there is no \
+                      separate sequence for either.",
+                     NoBold,
+                     "22m");
+derive_sgr_sequence!("Undo fainted text.\n\nNote that this also undoes bold text: there is no \
+                      separate sequence for either.",
+                     NoFaint,
+                     "22m");
+derive_sgr_sequence!("Undo italic text.", NoItalic, "23m");
+derive_sgr_sequence!("Undo underlined text, in any underline style.", NoUnderline, "24m");
+derive_sgr_sequence!("Undo blinking and rapidly blinking text.", NoBlink, "25m");
+derive_sgr_sequence!("Undo inverted colors (negative mode).", NoInvert, "27m");
+derive_sgr_sequence!("Undo hidden text.", NoHidden, "28m");
+derive_sgr_sequence!("Undo crossed out text.", NoCrossedOut, "29m");
+derive_sgr_sequence!("Undo framed and encircled text.", NoFramed, "54m");
+derive_sgr_sequence!("Undo overlined text.", NoOverline, "55m");
+derive_sgr_sequence!("Undo superscript and subscript text.", NoSuperscript, "75m");
+
+/// A set of text attributes, such as bold or italic.
+///
+/// Attributes are combined with `|`:
+///
+/// ```rust
+/// use termion::style::Attributes;
+///
+/// let attrs = Attributes::BOLD | Attributes::ITALIC;
+/// assert!(attrs.contains(Attributes::BOLD));
+/// assert!(!attrs.contains(Attributes::UNDERLINE));
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Attributes(u32);
+
+impl Attributes {
+    /// Bold text.
+    pub const BOLD: Attributes = Attributes(1 << 0);
+    /// Fainted text.
+    pub const FAINT: Attributes = Attributes(1 << 1);
+    /// Italic text.
+    pub const ITALIC: Attributes = Attributes(1 << 2);
+    /// Underlined text.
+    pub const UNDERLINE: Attributes = Attributes(1 << 3);
+    /// Blinking text.
+    pub const BLINK: Attributes = Attributes(1 << 4);
+    /// Inverted colors.
+    pub const INVERT: Attributes = Attributes(1 << 5);
+    /// Crossed out text.
+    pub const CROSSED_OUT: Attributes = Attributes(1 << 6);
+    /// Framed text.
+    pub const FRAMED: Attributes = Attributes(1 << 7);
+    /// Double underlined text.
+    pub const DOUBLE_UNDERLINE: Attributes = Attributes(1 << 8);
+    /// Rapidly blinking text.
+    pub const RAPID_BLINK: Attributes = Attributes(1 << 9);
+    /// Hidden text.
+    pub const HIDDEN: Attributes = Attributes(1 << 10);
+    /// Encircled text.
+    pub const ENCIRCLED: Attributes = Attributes(1 << 11);
+    /// Overlined text.
+    pub const OVERLINE: Attributes = Attributes(1 << 12);
+    /// Superscript text.
+    pub const SUPERSCRIPT: Attributes = Attributes(1 << 13);
+    /// Subscript text.
+    pub const SUBSCRIPT: Attributes = Attributes(1 << 14);
+    /// Curly underlined text.
+    pub const CURLY_UNDERLINE: Attributes = Attributes(1 << 15);
+    /// Dotted underlined text.
+    pub const DOTTED_UNDERLINE: Attributes = Attributes(1 << 16);
+    /// Dashed underlined text.
+    pub const DASHED_UNDERLINE: Attributes = Attributes(1 << 17);
+
+    /// The SGR parameters enabling and disabling each attribute.
+    const CODES: &'static [(Attributes, &'static str, &'static str)] =
+        &[(Attributes::BOLD, "1", "22"),
+          (Attributes::FAINT, "2", "22"),
+          (Attributes::ITALIC, "3", "23"),
+          (Attributes::UNDERLINE, "4", "24"),
+          (Attributes::DOUBLE_UNDERLINE, "21", "24"),
+          (Attributes::CURLY_UNDERLINE, "4:3", "24"),
+          (Attributes::DOTTED_UNDERLINE, "4:4", "24"),
+          (Attributes::DASHED_UNDERLINE, "4:5", "24"),
+          (Attributes::BLINK, "5", "25"),
+          (Attributes::RAPID_BLINK, "6", "25"),
+          (Attributes::INVERT, "7", "27"),
+          (Attributes::HIDDEN, "8", "28"),
+          (Attributes::CROSSED_OUT, "9", "29"),
+          (Attributes::FRAMED, "51", "54"),
+          (Attributes::ENCIRCLED, "52", "54"),
+          (Attributes::OVERLINE, "53", "55"),
+          (Attributes::SUPERSCRIPT, "73", "75"),
+          (Attributes::SUBSCRIPT, "74", "75")];
+
+    /// The empty set.
+    pub fn empty() -> Attributes {
+        Attributes(0)
+    }
+
+    /// Is this set empty?
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Does this set contain all the attributes of `other`?
+    pub fn contains(self, other: Attributes) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Add the attributes of `other` to this set.
+    pub fn insert(&mut self, other: Attributes) {
+        self.0 |= other.0;
+    }
+
+    /// Remove the attributes of `other` from this set.
+    pub fn remove(&mut self, other: Attributes) {
+        self.0 &= !other.0;
+    }
+}
+
+impl ops::BitOr for Attributes {
+    type Output = Attributes;
+
+    fn bitor(self, other: Attributes) -> Attributes {
+        Attributes(self.0 | other.0)
+    }
+}
+
+impl ops::BitOrAssign for Attributes {
+    fn bitor_assign(&mut self, other: Attributes) {
+        self.0 |= other.0;
+    }
+}
+
+/// A text style: optional colors and a set of attributes.
+///
+/// Displaying a style emits a single SGR sequence applying it on top of the current one (nothing
+/// at all for the default, empty style). Use `transition` to go from one style to another.
+///
+/// An unset (`None`) color has two meanings, depending on how the style is used:
+///
+/// - When displaying a style, or patching another one with it, the color is left unchanged.
+/// - In `transition`, both styles describe the complete state of the terminal, so an unset color
+///   is the default color.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color::{AnsiValue, Rgb};
+/// use termion::style::{Attributes, Style};
+///
+/// let warning = Style::new()
+///     .fg(Rgb(255, 0, 0))
+///     .bg(AnsiValue(4))
+///     .attrs(Attributes::BOLD | Attributes::UNDERLINE);
+/// assert_eq!(warning.to_string(), "\x1B[1;4;38;2;255;0;0;48;5;4m");
+///
+/// let emphasized = warning.patch(Style::new().attrs(Attributes::ITALIC));
+/// println!("{}Warning!{}", emphasized, emphasized.transition(&Style::new()));
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    /// The foreground color, if any.
+    ///
+    /// `None` leaves the color unchanged when displaying or patching, and is the default color
+    /// in transitions.
+    pub fg: Option<AnyColor>,
+    /// The background color, if any.
+    ///
+    /// `None` leaves the color unchanged when displaying or patching, and is the default color
+    /// in transitions.
+    pub bg: Option<AnyColor>,
+    /// The text attributes.
+    pub attrs: Attributes,
+}
+
+impl Style {
+    /// The empty style, which changes nothing.
+    pub fn new() -> Style {
+        Style::default()
+    }
+
+    /// Set the foreground color.
+    pub fn fg<C: Into<AnyColor>>(mut self, color: C) -> Style {
+        self.fg = Some(color.into());
+        self
+    }
+
+    /// Set the background color.
+    pub fn bg<C: Into<AnyColor>>(mut self, color: C) -> Style {
+        self.bg = Some(color.into());
+        self
+    }
+
+    /// Add some attributes.
+    pub fn attrs(mut self, attrs: Attributes) -> Style {
+        self.attrs.insert(attrs);
+        self
+    }
+
+    /// Apply `other` on top of this style.
+    ///
+    /// The colors of `other` take precedence when set, and the attributes are combined.
+    pub fn patch(self, other: Style) -> Style {
+        Style {
+            fg: other.fg.or(self.fg),
+            bg: other.bg.or(self.bg),
+            attrs: self.attrs | other.attrs,
+        }
+    }
+
+    /// Get the shortest sequence changing the terminal from this style to `to`.
+    ///
+    /// Both styles are taken as the complete state of the terminal, so unset colors are the
+    /// default colors: e.g. going from a red foreground to an unset one emits `39`.
+    pub fn transition(&self, to: &Style) -> Transition {
+        Transition {
+            from: *self,
+            to: *to,
+        }
+    }
+
+    /// Wrap some content, so that it is displayed with this style.
+    ///
+    /// See `Styled` for details.
+    pub fn paint<T: fmt::Display>(self, content: T) -> Styled<T> {
+        Styled::new(self, content)
+    }
+
+    /// The SGR parameters enabling this style.
+    fn params(&self) -> Vec<String> {
+        let mut params: Vec<String> = Attributes::CODES.iter()
+            .filter(|&&(attr, _, _)| self.attrs.contains(attr))
+            .map(|&(_, on, _)| on.to_owned())
+            .collect();
+        params.extend(self.fg.map(|c| c.fg_params()));
+        params.extend(self.bg.map(|c| c.bg_params()));
+        params
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_sgr(f, &self.params())
+    }
+}
+
+/// A change from one style to another.
+///
+/// This is obtained through `Style::transition`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Transition {
+    from: Style,
+    to: Style,
+}
+
+impl Transition {
+    /// The SGR parameters changing the style incrementally.
+    fn incremental_params(&self) -> Vec<String> {
+        let (from, to) = (self.from.attrs, self.to.attrs);
+        let mut params = Vec::new();
+        let mut enabled = from;
+
+        // Some attributes share the same disabling code (e.g. bold and faint), so they may need
+        // to be enabled again.
+        for &(attr, _, off) in Attributes::CODES {
+            if enabled.contains(attr) && !to.contains(attr) {
+                for &(other, _, other_off) in Attributes::CODES {
+                    if other_off == off {
+                        enabled.remove(other);
+                    }
+                }
+                if !params.contains(&off.to_owned()) {
+                    params.push(off.to_owned());
+                }
+            }
+        }
+        for &(attr, on, _) in Attributes::CODES {
+            if to.contains(attr) && !enabled.contains(attr) {
+                params.push(on.to_owned());
+            }
+        }
+
+        let reset = AnyColor::Reset;
+        let (from_fg, to_fg) = (self.from.fg.unwrap_or(reset), self.to.fg.unwrap_or(reset));
+        if from_fg != to_fg {
+            params.push(to_fg.fg_params());
+        }
+        let (from_bg, to_bg) = (self.from.bg.unwrap_or(reset), self.to.bg.unwrap_or(reset));
+        if from_bg != to_bg {
+            params.push(to_bg.bg_params());
+        }
+
+        params
+    }
+
+    /// The SGR parameters resetting everything, and then enabling the new style.
+    fn reset_params(&self) -> Vec<String> {
+        let mut params = vec!["0".to_owned()];
+        params.extend(self.to
+            .params()
+            .into_iter()
+            .filter(|p| p != "39" && p != "49"));
+        params
+    }
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let incremental = self.incremental_params();
+        let reset = self.reset_params();
+        let len = |params: &[String]| params.iter().map(|p| p.len() + 1).sum::<usize>();
+
+        if len(&incremental) <= len(&reset) {
+            write_sgr(f, &incremental)
+        } else {
+            write_sgr(f, &reset)
+        }
+    }
+}
+
+thread_local! {
+    /// The style applied by the `Styled` values currently being displayed.
+    static CURRENT_STYLE: Cell<Style> = Cell::new(Style::new());
+}
+
+/// Some content displayed with a style.
+///
+/// Displaying it applies the style, writes the content and then restores only what the style
+/// changed, so colors and attributes never bleed into what comes next. Styled values can be
+/// nested: the inner style is applied on top of the outer one, and the outer one is restored
+/// afterwards. Note that nesting only works if the inner value is displayed while the outer one
+/// is (e.g. through `format_args!`), not if it was already formatted to a string.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color::{AnsiValue, AnyColor};
+/// use termion::style::{Attributes, Style};
+///
+/// let red = Style::new().fg(AnyColor::Ansi(AnsiValue(1)));
+/// let bold = Style::new().attrs(Attributes::BOLD);
+///
+/// let text = red.paint(format_args!("error: {}!", bold.paint("oops"))).to_string();
+/// assert_eq!(text, "\x1B[38;5;1merror: \x1B[1moops\x1B[22m!\x1B[39m");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Styled<T> {
+    style: Style,
+    content: T,
+}
+
+impl<T: fmt::Display> Styled<T> {
+    /// Wrap some content, so that it is displayed with the given style.
+    pub fn new(style: Style, content: T) -> Styled<T> {
+        Styled {
+            style: style,
+            content: content,
+        }
+    }
+
+    /// Get the style.
+    pub fn style(&self) -> Style {
+        self.style
+    }
+
+    /// Get the content.
+    pub fn content(&self) -> &T {
+        &self.content
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Styled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let outer = CURRENT_STYLE.with(|current| current.get());
+        let inner = outer.patch(self.style);
+
+        // Never reset everything, as this would also undo styles applied by other means.
+        write_sgr(f, &outer.transition(&inner).incremental_params())?;
+        CURRENT_STYLE.with(|current| current.set(inner));
+        let res = self.content.fmt(f);
+        CURRENT_STYLE.with(|current| current.set(outer));
+        res?;
+        write_sgr(f, &inner.transition(&outer).incremental_params())
+    }
+}
+
+/// Write a single SGR sequence with the given parameters, if there are any.
+fn write_sgr(f: &mut fmt::Formatter, params: &[String]) -> fmt::Result {
+    if params.is_empty() || !color::colors_enabled() {
+        Ok(())
+    } else {
+        write!(f, csi!("{}m"), params.join(";"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use color::{AnsiValue, Rgb};
+
+    #[test]
+    fn test_sequences() {
+        assert_eq!(NoBold.to_string(), "\x1B[22m");
+        assert_eq!(DoubleUnderline.to_string(), "\x1B[21m");
+        assert_eq!(Hidden.to_string(), "\x1B[8m");
+        assert_eq!(Overline.to_string(), "\x1B[53m");
+        assert_eq!(CurlyUnderline.to_string(), "\x1B[4:3m");
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Style::new().to_string(), "");
+        assert_eq!(Style::new().attrs(Attributes::BOLD).to_string(), "\x1B[1m");
+        assert_eq!(Style::new().fg(AnyColor::Reset).bg(AnyColor::Rgb(Rgb(1, 2, 3))).to_string(),
+                   "\x1B[39;48;2;1;2;3m");
+    }
+
+    #[test]
+    fn test_patch() {
+        let red = AnyColor::Ansi(AnsiValue(1));
+        let blue = AnyColor::Ansi(AnsiValue(4));
+        let base = Style::new().fg(red).bg(red).attrs(Attributes::BOLD);
+        let patched = base.patch(Style::new().fg(blue).attrs(Attributes::ITALIC));
+
+        assert_eq!(patched,
+                   Style::new().fg(blue).bg(red).attrs(Attributes::BOLD | Attributes::ITALIC));
+        assert_eq!(base.patch(Style::new()), base);
+    }
+
+    #[test]
+    fn test_transition() {
+        let red = AnyColor::Ansi(AnsiValue(1));
+        let bold = Style::new().attrs(Attributes::BOLD);
+        let bold_faint = Style::new().attrs(Attributes::BOLD | Attributes::FAINT);
+
+        assert_eq!(bold.transition(&bold).to_string(), "");
+        assert_eq!(Style::new().transition(&bold).to_string(), "\x1B[1m");
+        assert_eq!(bold.fg(red).transition(&Style::new().fg(red)).to_string(), "\x1B[22m");
+        // Bold and faint are disabled together.
+        assert_eq!(bold_faint.fg(red).transition(&bold.fg(red)).to_string(),
+                   "\x1B[22;1m");
+        // Switching underline styles.
+        let red_double = Style::new().fg(red).attrs(Attributes::DOUBLE_UNDERLINE);
+        assert_eq!(red_double.transition(&Style::new().fg(red).attrs(Attributes::UNDERLINE))
+                       .to_string(),
+                   "\x1B[24;4m");
+        // Unset colors are the default ones.
+        assert_eq!(bold.transition(&bold.fg(red)).to_string(), "\x1B[38;5;1m");
+        assert_eq!(bold.fg(red).transition(&bold).to_string(), "\x1B[39m");
+        // Resetting is shorter.
+        assert_eq!(bold.transition(&Style::new()).to_string(), "\x1B[0m");
+        assert_eq!(bold.fg(red).transition(&Style::new().attrs(Attributes::ITALIC)).to_string(),
+                   "\x1B[0;3m");
+    }
+
+    #[test]
+    fn test_styled() {
+        let red = AnyColor::Ansi(AnsiValue(1));
+        let blue = AnyColor::Ansi(AnsiValue(4));
+
+        assert_eq!(Style::new().paint("plain").to_string(), "plain");
+        assert_eq!(Style::new().fg(red).paint("red").to_string(),
+                   "\x1B[38;5;1mred\x1B[39m");
+
+        // Nested styles restore the outer style, not the defaults.
+        let inner = Style::new().fg(blue).attrs(Attributes::UNDERLINE).paint("inner");
+        let outer = Style::new().fg(red).paint(format_args!("a{}b", inner)).to_string();
+        assert_eq!(outer,
+                   "\x1B[38;5;1ma\x1B[4;38;5;4minner\x1B[24;38;5;1mb\x1B[39m");
+
+        // Setting the same style again changes nothing.
+        let same = Style::new().fg(red).paint("same");
+        assert_eq!(Style::new().fg(red).paint(same).to_string(),
+                   "\x1B[38;5;1msame\x1B[39m");
+    }
+}
@@ -1,11 +1,13 @@
 use std::io;
+use std::os::unix::io::RawFd;
 
 use super::{cvt, syscall, Termios};
 
-pub fn get_terminal_attr() -> io::Result<Termios> {
+/// Get the attributes of the terminal the given file descriptor refers to.
+pub fn get_terminal_attr(fd: RawFd) -> io::Result<Termios> {
     let mut termios = Termios::default();
 
-    let fd = cvt(syscall::dup(0, b"termios"))?;
+    let fd = cvt(syscall::dup(fd, b"termios"))?;
     let res = cvt(syscall::read(fd, &mut termios));
     let _ = syscall::close(fd);
 
@@ -16,8 +18,9 @@ pub fn get_terminal_attr() -> io::Result<Termios> {
     }
 }
 
-pub fn set_terminal_attr(termios: &Termios) -> io::Result<()> {
-    let fd = cvt(syscall::dup(0, b"termios"))?;
+/// Set the attributes of the terminal the given file descriptor refers to.
+pub fn set_terminal_attr(fd: RawFd, termios: &Termios) -> io::Result<()> {
+    let fd = cvt(syscall::dup(fd, b"termios"))?;
     let res = cvt(syscall::write(fd, termios));
     let _ = syscall::close(fd);
 
@@ -1,12 +1,22 @@
 use std::io;
+use std::os::unix::io::AsRawFd;
 
 use super::{cvt, redox_termios, syscall};
 
 /// Get the size of the terminal.
 pub fn terminal_size() -> io::Result<(u16, u16)> {
+    get_size(1)
+}
+
+/// Get the size of the terminal the given stream refers to.
+pub fn terminal_size_of<T: AsRawFd>(stream: &T) -> io::Result<(u16, u16)> {
+    get_size(stream.as_raw_fd())
+}
+
+fn get_size(fd: usize) -> io::Result<(u16, u16)> {
     let mut winsize = redox_termios::Winsize::default();
 
-    let fd = cvt(syscall::dup(1, b"winsize"))?;
+    let fd = cvt(syscall::dup(fd, b"winsize"))?;
     let res = cvt(syscall::read(fd, &mut winsize));
     let _ = syscall::close(fd);
 
@@ -16,3 +26,33 @@ pub fn terminal_size() -> io::Result<(u16, u16)> {
         Err(io::Error::new(io::ErrorKind::Other, "Unable to get the terminal size."))
     }
 }
+
+/// Get the size of the terminal in pixels, as reported by the kernel.
+///
+/// Redox doesn't track the pixel size, so this is always `(0, 0)`. See `size::DetectPixelSize`
+/// for a version which falls back to asking the terminal itself.
+pub fn terminal_size_pixels() -> io::Result<(u16, u16)> {
+    terminal_size().map(|_| (0, 0))
+}
+
+/// Set the size of the terminal the given stream refers to.
+///
+/// `size` is in columns and rows. Redox doesn't track the pixel size, so `pixels` is ignored.
+pub fn set_terminal_size<T: AsRawFd>(stream: &T,
+                                     size: (u16, u16),
+                                     _pixels: (u16, u16))
+                                     -> io::Result<()> {
+    let mut winsize = redox_termios::Winsize::default();
+    winsize.ws_col = size.0;
+    winsize.ws_row = size.1;
+
+    let fd = cvt(syscall::dup(stream.as_raw_fd(), b"winsize"))?;
+    let res = cvt(syscall::write(fd, &winsize));
+    let _ = syscall::close(fd);
+
+    if res? == winsize.len() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, "Unable to set the terminal size."))
+    }
+}
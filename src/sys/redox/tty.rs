@@ -1,7 +1,8 @@
 use std::{env, fs, io};
 use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
 
-use super::syscall;
+use super::{cvt, syscall};
 
 /// Is this stream a TTY?
 pub fn is_tty<T: AsRawFd>(stream: &T) -> bool {
@@ -20,3 +21,27 @@ pub fn get_tty() -> io::Result<fs::File> {
     let tty = try!(env::var("TTY").map_err(|x| io::Error::new(io::ErrorKind::NotFound, x)));
     fs::OpenOptions::new().read(true).write(true).open(tty)
 }
+
+/// Get the path of the TTY device this stream refers to.
+pub fn ttyname<T: AsRawFd>(stream: &T) -> io::Result<PathBuf> {
+    if !is_tty(stream) {
+        return Err(io::Error::new(io::ErrorKind::Other, "Not a TTY."));
+    }
+
+    let mut buf = [0; 4096];
+    let count = cvt(syscall::fpath(stream.as_raw_fd(), &mut buf))?;
+    let path = String::from_utf8_lossy(&buf[..count]).into_owned();
+    Ok(PathBuf::from(path))
+}
+
+/// Is this process in the foreground process group of its controlling terminal?
+///
+/// Redox has no job control, so this is always `true`.
+pub fn is_foreground() -> io::Result<bool> {
+    Ok(true)
+}
+
+/// Get the path of the controlling terminal of this session.
+pub fn controlling_tty() -> io::Result<PathBuf> {
+    env::var("TTY").map(PathBuf::from).map_err(|x| io::Error::new(io::ErrorKind::NotFound, x))
+}
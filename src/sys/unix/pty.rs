@@ -0,0 +1,234 @@
+//! Pseudo-terminals.
+//!
+//! A pseudo-terminal is a pair of devices: the slave side behaves exactly like a real terminal to
+//! the program attached to it, while the master side is used to read what that program draws and
+//! to write its input. This can be used to embed other programs (e.g. a shell) or to test
+//! terminal applications against a real TTY.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use termion::pty::Pty;
+//! use std::io::Read;
+//! use std::process::Command;
+//!
+//! fn main() {
+//!     let mut pty = Pty::open().unwrap();
+//!     pty.resize(80, 24).unwrap();
+//!
+//!     let mut child = pty.spawn(Command::new("ls")).unwrap();
+//!     child.wait().unwrap();
+//!
+//!     let mut output = [0; 4096];
+//!     let len = pty.read(&mut output).unwrap();
+//!     println!("{}", String::from_utf8_lossy(&output[..len]));
+//! }
+//! ```
+
+use std::{fs, io};
+use std::ffi::{CStr, OsStr};
+use std::io::{Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+use super::{cvt, libc};
+use super::size::set_terminal_size;
+
+/// The master side of a pseudo-terminal.
+///
+/// Reading gives the output of the program attached to the slave side, and writing sends it
+/// input, as if typed by the user.
+///
+/// Note that on Linux, reading from the master fails with an `EIO` error once every slave file
+/// descriptor is closed (i.e. the attached programs exited).
+pub struct Pty {
+    master: fs::File,
+    slave_path: PathBuf,
+}
+
+impl Pty {
+    /// Open a new pseudo-terminal.
+    pub fn open() -> io::Result<Pty> {
+        unsafe {
+            let fd = cvt(libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY))?;
+            // Take ownership right away, so that the descriptor is closed on error.
+            let master = fs::File::from_raw_fd(fd);
+
+            cvt(libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC))?;
+            cvt(libc::grantpt(fd))?;
+            cvt(libc::unlockpt(fd))?;
+
+            Ok(Pty {
+                slave_path: slave_name(fd)?,
+                master: master,
+            })
+        }
+    }
+
+    /// Get the path of the slave device (e.g. `/dev/pts/3`).
+    pub fn slave_path(&self) -> &Path {
+        &self.slave_path
+    }
+
+    /// Open the slave side of the pseudo-terminal.
+    ///
+    /// This never makes it the controlling terminal of this process, even for a session leader
+    /// without one.
+    pub fn open_slave(&self) -> io::Result<fs::File> {
+        fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(&self.slave_path)
+    }
+
+    /// Set the size of the pseudo-terminal, in columns and rows.
+    ///
+    /// The program attached to the slave side gets notified through `SIGWINCH`. Use
+    /// `set_terminal_size` to also set the size in pixels.
+    pub fn resize(&self, cols: u16, rows: u16) -> io::Result<()> {
+        set_terminal_size(self, (cols, rows), (0, 0))
+    }
+
+    /// Spawn a command with the slave side as its standard streams and controlling terminal.
+    ///
+    /// The child is started in a new session, so it behaves exactly like a program started from a
+    /// terminal emulator.
+    pub fn spawn(&self, mut command: Command) -> io::Result<Child> {
+        let slave = self.open_slave()?;
+
+        command.stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+
+        unsafe {
+            command.pre_exec(|| {
+                // Standard streams are already redirected to the slave at this point.
+                cvt(libc::setsid())?;
+                cvt(libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY as _, 0))?;
+                Ok(())
+            });
+        }
+
+        command.spawn()
+    }
+
+    /// Create another handle to the same pseudo-terminal.
+    ///
+    /// This is useful for reading and writing from different threads.
+    pub fn try_clone(&self) -> io::Result<Pty> {
+        Ok(Pty {
+            master: self.master.try_clone()?,
+            slave_path: self.slave_path.clone(),
+        })
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn slave_name(fd: RawFd) -> io::Result<PathBuf> {
+    let mut buf = [0 as libc::c_char; 256];
+    let res = libc::ptsname_r(fd, buf.as_mut_ptr(), buf.len());
+    if res != 0 {
+        return Err(io::Error::from_raw_os_error(res));
+    }
+
+    let name = CStr::from_ptr(buf.as_ptr());
+    Ok(PathBuf::from(OsStr::from_bytes(name.to_bytes())))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+unsafe fn slave_name(fd: RawFd) -> io::Result<PathBuf> {
+    let name = libc::ptsname(fd);
+    if name.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+
+    let name = CStr::from_ptr(name);
+    Ok(PathBuf::from(OsStr::from_bytes(name.to_bytes())))
+}
+
+impl AsRawFd for Pty {
+    fn as_raw_fd(&self) -> RawFd {
+        self.master.as_raw_fd()
+    }
+}
+
+impl Read for Pty {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.master.read(buf)
+    }
+}
+
+impl Write for Pty {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.master.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.master.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::size::terminal_size_of;
+    use std::process::Command;
+
+    /// Read everything the attached programs wrote, until they are all gone.
+    fn read_all(pty: &mut Pty) -> String {
+        let mut output = Vec::new();
+        let mut buf = [0; 1024];
+        loop {
+            match pty.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => output.extend_from_slice(&buf[..n]),
+            }
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_spawn() {
+        let mut pty = Pty::open().unwrap();
+        pty.resize(80, 24).unwrap();
+
+        let mut command = Command::new("sh");
+        command.args(&["-c", "tty; stty size"]);
+        let mut child = pty.spawn(command).unwrap();
+        assert!(child.wait().unwrap().success());
+
+        let output = read_all(&mut pty);
+        let mut lines = output.lines();
+        assert_eq!(Path::new(lines.next().unwrap()), pty.slave_path());
+        assert_eq!(lines.next(), Some("24 80"));
+    }
+
+    #[test]
+    fn test_set_terminal_size() {
+        let pty = Pty::open().unwrap();
+        set_terminal_size(&pty, (100, 30), (800, 480)).unwrap();
+        assert_eq!(terminal_size_of(&pty).unwrap(), (100, 30));
+
+        pty.resize(120, 40).unwrap();
+        assert_eq!(terminal_size_of(&pty.open_slave().unwrap()).unwrap(), (120, 40));
+    }
+
+    #[test]
+    fn test_input() {
+        let mut pty = Pty::open().unwrap();
+
+        let mut command = Command::new("head");
+        command.args(&["-n", "1"]);
+        let mut child = pty.spawn(command).unwrap();
+        pty.write_all(b"hello\n").unwrap();
+        assert!(child.wait().unwrap().success());
+
+        // The line is echoed by the terminal, and then printed by `head`.
+        assert_eq!(read_all(&mut pty), "hello\r\nhello\r\n");
+    }
+}
@@ -1,20 +1,60 @@
 use std::{io, mem};
+use std::os::unix::io::{AsRawFd, RawFd};
 
 use super::cvt;
-use super::libc::{c_ushort, ioctl, STDOUT_FILENO, TIOCGWINSZ};
+use super::libc::{c_ushort, ioctl, STDOUT_FILENO, TIOCGWINSZ, TIOCSWINSZ};
 
 #[repr(C)]
 struct TermSize {
     row: c_ushort,
     col: c_ushort,
-    _x: c_ushort,
-    _y: c_ushort,
+    x: c_ushort,
+    y: c_ushort,
 }
-/// Get the size of the terminal.
-pub fn terminal_size() -> io::Result<(u16, u16)> {
+
+fn get_size(fd: RawFd) -> io::Result<TermSize> {
     unsafe {
         let mut size: TermSize = mem::zeroed();
-        cvt(ioctl(STDOUT_FILENO, TIOCGWINSZ, &mut size as *mut _))?;
-        Ok((size.col as u16, size.row as u16))
+        cvt(ioctl(fd, TIOCGWINSZ, &mut size as *mut _))?;
+        Ok(size)
     }
 }
+
+/// Get the size of the terminal.
+pub fn terminal_size() -> io::Result<(u16, u16)> {
+    let size = get_size(STDOUT_FILENO)?;
+    Ok((size.col as u16, size.row as u16))
+}
+
+/// Get the size of the terminal the given stream refers to.
+pub fn terminal_size_of<T: AsRawFd>(stream: &T) -> io::Result<(u16, u16)> {
+    let size = get_size(stream.as_raw_fd())?;
+    Ok((size.col as u16, size.row as u16))
+}
+
+/// Get the size of the terminal in pixels, as reported by the kernel.
+///
+/// Many terminals don't tell the kernel their pixel size, in which case this is `(0, 0)`. See
+/// `size::DetectPixelSize` for a version which falls back to asking the terminal itself.
+pub fn terminal_size_pixels() -> io::Result<(u16, u16)> {
+    let size = get_size(STDOUT_FILENO)?;
+    Ok((size.x as u16, size.y as u16))
+}
+
+/// Set the size of the terminal the given stream refers to.
+///
+/// `size` is in columns and rows, `pixels` is the width and height in pixels (or `(0, 0)` if
+/// unknown). This is mostly useful on the master side of a pseudo-terminal, to forward a resize to
+/// the program running in it, which gets notified through `SIGWINCH`.
+pub fn set_terminal_size<T: AsRawFd>(stream: &T,
+                                     size: (u16, u16),
+                                     pixels: (u16, u16))
+                                     -> io::Result<()> {
+    let size = TermSize {
+        row: size.1 as c_ushort,
+        col: size.0 as c_ushort,
+        x: pixels.0 as c_ushort,
+        y: pixels.1 as c_ushort,
+    };
+    cvt(unsafe { ioctl(stream.as_raw_fd(), TIOCSWINSZ, &size as *const _) }).and(Ok(()))
+}
@@ -1,7 +1,10 @@
 use std::{fs, io};
-use std::os::unix::io::AsRawFd;
+use std::ffi::{CStr, OsStr};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::PathBuf;
 
-use super::libc;
+use super::{cvt, libc};
 
 
 /// Is this stream a TTY?
@@ -15,3 +18,45 @@ pub fn is_tty<T: AsRawFd>(stream: &T) -> bool {
 pub fn get_tty() -> io::Result<fs::File> {
     fs::OpenOptions::new().read(true).write(true).open("/dev/tty")
 }
+
+/// Get the path of the TTY device this stream refers to (e.g. `/dev/pts/3`).
+pub fn ttyname<T: AsRawFd>(stream: &T) -> io::Result<PathBuf> {
+    fd_ttyname(stream.as_raw_fd())
+}
+
+fn fd_ttyname(fd: RawFd) -> io::Result<PathBuf> {
+    let mut buf = [0 as libc::c_char; 256];
+    let res = unsafe { libc::ttyname_r(fd, buf.as_mut_ptr(), buf.len()) };
+    if res != 0 {
+        return Err(io::Error::from_raw_os_error(res));
+    }
+
+    let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    Ok(PathBuf::from(OsStr::from_bytes(name.to_bytes())))
+}
+
+/// Is this process in the foreground process group of its controlling terminal?
+///
+/// A program running in the background (e.g. started with `&` in a shell) should not draw to
+/// or read from the terminal; doing so gets it stopped by `SIGTTOU` or `SIGTTIN`.
+pub fn is_foreground() -> io::Result<bool> {
+    let tty = get_tty()?;
+    let foreground = cvt(unsafe { libc::tcgetpgrp(tty.as_raw_fd()) })?;
+    Ok(foreground == unsafe { libc::getpgrp() })
+}
+
+/// Get the path of the controlling terminal of this session (e.g. `/dev/pts/3`).
+///
+/// Unlike `/dev/tty`, which is merely an alias, this is the actual device. It is found through
+/// whichever standard stream is attached to it.
+pub fn controlling_tty() -> io::Result<PathBuf> {
+    let session = cvt(unsafe { libc::getsid(0) })?;
+    for &fd in &[libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        if unsafe { libc::tcgetsid(fd) } == session {
+            return fd_ttyname(fd);
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::NotFound,
+                       "No standard stream is attached to the controlling terminal."))
+}
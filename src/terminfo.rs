@@ -0,0 +1,229 @@
+//! Reading capabilities from the compiled terminfo database.
+//!
+//! Only the capabilities needed by termion are extracted: the `colors` number, and the extended
+//! (user-defined) booleans and numbers, such as `RGB`.
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// The index of `colors` among the standard numbers.
+const COLORS: usize = 13;
+
+/// The capabilities of a terminal.
+#[derive(Debug, Default, PartialEq)]
+pub struct Terminfo {
+    /// The `colors` number, if present.
+    pub colors: Option<i32>,
+    /// The extended booleans which are set.
+    pub ext_flags: Vec<String>,
+    /// The extended numbers.
+    pub ext_numbers: Vec<(String, i32)>,
+}
+
+impl Terminfo {
+    /// Load the entry of the given terminal from the database.
+    pub fn load(term: &str) -> Option<Terminfo> {
+        let first = term.chars().next()?;
+        if term.contains('/') {
+            return None;
+        }
+
+        for dir in search_dirs() {
+            // Most systems use the first letter, macOS uses its hex code.
+            for sub in &[first.to_string(), format!("{:x}", first as u32)] {
+                let mut data = Vec::new();
+                let path = dir.join(sub).join(term);
+                if File::open(path).and_then(|mut f| f.read_to_end(&mut data)).is_ok() {
+                    return parse(&data);
+                }
+            }
+        }
+        None
+    }
+
+    /// Is the extended boolean `name` set?
+    pub fn flag(&self, name: &str) -> bool {
+        self.ext_flags.iter().any(|flag| flag == name)
+    }
+
+    /// Get the extended number `name`.
+    pub fn number(&self, name: &str) -> Option<i32> {
+        self.ext_numbers.iter().find(|&(n, _)| n == name).map(|&(_, value)| value)
+    }
+}
+
+/// The directories to search, in order.
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = env::var_os("TERMINFO") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".terminfo"));
+    }
+    if let Ok(list) = env::var("TERMINFO_DIRS") {
+        for dir in list.split(':').filter(|dir| !dir.is_empty()) {
+            dirs.push(PathBuf::from(dir));
+        }
+    }
+    for dir in &["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo", "/usr/lib/terminfo"] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs
+}
+
+/// A cursor over the little-endian data of a compiled entry.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn i16(&mut self) -> Option<i32> {
+        self.bytes(2).map(|b| i16::from_le_bytes([b[0], b[1]]) as i32)
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.bytes(4).map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Read a count or a size from the header.
+    fn count(&mut self) -> Option<usize> {
+        let n = self.i16()?;
+        if n < 0 { None } else { Some(n as usize) }
+    }
+
+    /// Skip a padding byte, if needed to get to an even position.
+    fn align(&mut self) {
+        if self.pos % 2 == 1 {
+            self.pos += 1;
+        }
+    }
+}
+
+/// Parse a compiled terminfo entry.
+fn parse(data: &[u8]) -> Option<Terminfo> {
+    let mut reader = Reader { data: data, pos: 0 };
+
+    let wide = match reader.i16()? {
+        0o432 => false,
+        0o1036 => true,
+        _ => return None,
+    };
+    let names_size = reader.count()?;
+    let bools = reader.count()?;
+    let nums = reader.count()?;
+    let strings = reader.count()?;
+    let table_size = reader.count()?;
+
+    let mut info = Terminfo::default();
+
+    reader.bytes(names_size + bools)?;
+    reader.align();
+    for i in 0..nums {
+        let n = if wide { reader.i32()? } else { reader.i16()? };
+        if i == COLORS && n >= 0 {
+            info.colors = Some(n);
+        }
+    }
+    reader.bytes(strings * 2 + table_size)?;
+
+    // The extended capabilities are optional.
+    reader.align();
+    if reader.pos >= data.len() {
+        return Some(info);
+    }
+    let ext_bools = reader.count()?;
+    let ext_nums = reader.count()?;
+    let ext_strings = reader.count()?;
+    let _table_items = reader.count()?;
+    let ext_table_size = reader.count()?;
+
+    let flags = reader.bytes(ext_bools)?;
+    reader.align();
+    let mut numbers = Vec::with_capacity(ext_nums);
+    for _ in 0..ext_nums {
+        numbers.push(if wide { reader.i32()? } else { reader.i16()? });
+    }
+    // Skip the offsets of the string values and of the names.
+    reader.bytes((ext_strings + ext_bools + ext_nums + ext_strings) * 2)?;
+    let table = reader.bytes(ext_table_size)?;
+
+    // The names are the last strings of the table: booleans, then numbers, then strings.
+    let mut names: Vec<&[u8]> = table.split(|&b| b == 0).collect();
+    names.pop();
+    let count = ext_bools + ext_nums + ext_strings;
+    if names.len() < count {
+        return None;
+    }
+    let names = &names[names.len() - count..];
+    let name = |i: usize| String::from_utf8_lossy(names[i]).into_owned();
+
+    for (i, &flag) in flags.iter().enumerate() {
+        if flag == 1 {
+            info.ext_flags.push(name(i));
+        }
+    }
+    for (i, &n) in numbers.iter().enumerate() {
+        if n >= 0 {
+            info.ext_numbers.push((name(ext_bools + i), n));
+        }
+    }
+
+    Some(info)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        fn shorts(data: &mut Vec<u8>, values: &[i16]) {
+            for v in values {
+                data.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+
+        let mut data = Vec::new();
+
+        // Header: 1 boolean, 14 numbers, 1 string of 3 bytes.
+        shorts(&mut data, &[0o432, 5, 1, 14, 1, 3]);
+        data.extend_from_slice(b"test\0");
+        data.push(1);
+        shorts(&mut data, &[-1; 13]);
+        shorts(&mut data, &[256]);
+        shorts(&mut data, &[0]);
+        data.extend_from_slice(b"ab\0");
+
+        // Extended: 2 booleans, 1 number, 1 string.
+        data.push(0);
+        shorts(&mut data, &[2, 1, 1, 5, 17]);
+        data.extend_from_slice(&[1, 0]);
+        shorts(&mut data, &[24]);
+        shorts(&mut data, &[0, 0, 4, 7, 10]);
+        data.extend_from_slice(b"xyz\0RGB\0AX\0Nm\0Xs\0");
+
+        let info = parse(&data).unwrap();
+        assert_eq!(info.colors, Some(256));
+        assert!(info.flag("RGB"));
+        assert!(!info.flag("AX"));
+        assert_eq!(info.number("Nm"), Some(24));
+        assert_eq!(info.number("Xs"), None);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse(b""), None);
+        assert_eq!(parse(b"\x1A\x01\x05\x00"), None);
+        assert_eq!(parse(b"\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00"), None);
+    }
+}
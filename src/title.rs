@@ -0,0 +1,169 @@
+//! Setting the window title and icon name.
+//!
+//! # Example
+//!
+//! ```rust
+//! use termion::title::{SavedTitle, SetWindowTitle};
+//! use std::io::{Write, stdout};
+//!
+//! fn main() {
+//!     {
+//!         let mut stdout = SavedTitle::from(stdout());
+//!         write!(stdout, "{}", SetWindowTitle("Working...")).unwrap();
+//!     }
+//!     // The previous title is restored here.
+//! }
+//! ```
+
+use std::fmt::{self, Write as FmtWrite};
+use std::io::{self, Write};
+use std::ops;
+use osc::Sanitized;
+
+/// Set both the window title and the icon name.
+///
+/// Control characters are removed from the title.
+#[derive(Copy, Clone, Debug)]
+pub struct SetTitle<T: fmt::Display>(pub T);
+
+impl<T: fmt::Display> fmt::Display for SetTitle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1B]0;")?;
+        write!(Sanitized(f), "{}", self.0)?;
+        write!(f, "\x1B\\")
+    }
+}
+
+/// Set the icon name (i.e. the title of a minimized window, or of the tab in some terminals).
+///
+/// Control characters are removed from the name.
+#[derive(Copy, Clone, Debug)]
+pub struct SetIconName<T: fmt::Display>(pub T);
+
+impl<T: fmt::Display> fmt::Display for SetIconName<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1B]1;")?;
+        write!(Sanitized(f), "{}", self.0)?;
+        write!(f, "\x1B\\")
+    }
+}
+
+/// Set the window title.
+///
+/// Control characters are removed from the title.
+#[derive(Copy, Clone, Debug)]
+pub struct SetWindowTitle<T: fmt::Display>(pub T);
+
+impl<T: fmt::Display> fmt::Display for SetWindowTitle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1B]2;")?;
+        write!(Sanitized(f), "{}", self.0)?;
+        write!(f, "\x1B\\")
+    }
+}
+
+derive_csi_sequence!("Push the window title and icon name on the terminal's title stack.",
+                     PushTitle,
+                     "22;0t");
+derive_csi_sequence!("Pop the window title and icon name from the terminal's title stack.",
+                     PopTitle,
+                     "23;0t");
+
+/// A wrapper which saves the window title and icon name on creation, and restores them on drop.
+///
+/// This uses the title stack of the terminal (supported by xterm, VTE and others), so it works
+/// without knowing the current title. Terminals without a title stack ignore it.
+///
+/// Dropping never panics: if the output is gone (e.g. a closed pipe), the error is silently
+/// ignored. Use `finish` to find out whether restoring succeeded.
+pub struct SavedTitle<W: Write> {
+    /// The output target.
+    output: W,
+    /// Whether the title was already restored.
+    finished: bool,
+}
+
+impl<W: Write> SavedTitle<W> {
+    /// Save the title of the terminal, and wrap the provided output.
+    ///
+    /// # Panics
+    ///
+    /// This panics if saving the title fails. See `try_from` for a fallible version.
+    pub fn from(output: W) -> Self {
+        SavedTitle::try_from(output).expect("save the window title")
+    }
+
+    /// Save the title of the terminal, and wrap the provided output, returning an error if the
+    /// escape sequence could not be written.
+    pub fn try_from(mut output: W) -> io::Result<Self> {
+        write!(output, "{}", PushTitle)?;
+        Ok(SavedTitle {
+            output: output,
+            finished: false,
+        })
+    }
+
+    /// Restore the title of the terminal and flush the output.
+    ///
+    /// Once the escape sequence is written, dropping this wrapper does not write anything anymore,
+    /// even if flushing fails. If writing fails, restoring is attempted again on drop.
+    pub fn finish(&mut self) -> io::Result<()> {
+        write!(self.output, "{}", PopTitle)?;
+        self.finished = true;
+        self.output.flush()
+    }
+}
+
+impl<W: Write> Drop for SavedTitle<W> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = write!(self.output, "{}", PopTitle);
+            let _ = self.output.flush();
+        }
+    }
+}
+
+impl<W: Write> ops::Deref for SavedTitle<W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        &self.output
+    }
+}
+
+impl<W: Write> ops::DerefMut for SavedTitle<W> {
+    fn deref_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+}
+
+impl<W: Write> Write for SavedTitle<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_set_title() {
+        assert_eq!(SetTitle("a").to_string(), "\x1B]0;a\x1B\\");
+        assert_eq!(SetIconName(42).to_string(), "\x1B]1;42\x1B\\");
+        assert_eq!(SetWindowTitle("b\x1B\\\x07c\n\u{9c}d").to_string(),
+                   "\x1B]2;b\\cd\x1B\\");
+    }
+
+    #[test]
+    fn test_saved_title() {
+        let mut out = SavedTitle::try_from(Vec::new()).unwrap();
+        write!(out, "{}", SetWindowTitle("x")).unwrap();
+        out.finish().unwrap();
+        assert_eq!(&out[..], b"\x1B[22;0t\x1B]2;x\x1B\\\x1B[23;0t");
+    }
+}
@@ -0,0 +1,828 @@
+//! A virtual terminal, for testing what a program draws.
+//!
+//! `VirtualTerminal` is an in-memory `Write` sink which interprets the escape sequences emitted
+//! by this crate (cursor movement, clearing, scrolling, colors and styles, alternate screen,
+//! cursor saving and restoring) into a grid of cells, like a real terminal would.
+//!
+//! The line discipline of a TTY is not emulated: `\n` only moves the cursor down, like in raw
+//! mode. Every character takes up a single cell.
+//!
+//! # Example
+//!
+//! ```rust
+//! use termion::{clear, color, cursor};
+//! use termion::vt::VirtualTerminal;
+//! use std::io::Write;
+//!
+//! fn main() {
+//!     let mut term = VirtualTerminal::new(20, 3);
+//!     write!(term, "{}{}{}Hello", clear::All, cursor::Goto(3, 2), color::Fg(color::Red))
+//!         .unwrap();
+//!
+//!     assert_eq!(term.contents(), "\n  Hello");
+//!     assert_eq!(term.cell(3, 2).unwrap().ch, 'H');
+//!     assert_eq!(term.cursor(), (8, 2));
+//! }
+//! ```
+
+use std::io::{self, Write};
+use std::{cmp, mem, str};
+
+use color::{AnsiValue, AnyColor, Rgb};
+use style::Attributes;
+
+/// The maximum length of the parameters of a `CSI` sequence. Longer sequences are ignored.
+const MAX_CSI_LEN: usize = 256;
+
+pub use buffer::Cell;
+
+/// The graphic rendition used for new characters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct Pen {
+    fg: AnyColor,
+    bg: AnyColor,
+    attrs: Attributes,
+}
+
+/// A saved cursor, along with its graphic rendition.
+#[derive(Copy, Clone, Debug, Default)]
+struct SavedCursor {
+    x: u16,
+    y: u16,
+    pen: Pen,
+}
+
+/// A screen buffer.
+#[derive(Clone, Debug)]
+struct Grid {
+    rows: Vec<Vec<Cell>>,
+    saved: SavedCursor,
+}
+
+impl Grid {
+    fn new(cols: u16, rows: u16) -> Grid {
+        Grid {
+            rows: vec![vec![Cell::default(); cols as usize]; rows as usize],
+            saved: SavedCursor::default(),
+        }
+    }
+}
+
+/// The state of the escape sequence parser.
+#[derive(Clone, Debug)]
+enum State {
+    /// Printing text.
+    Ground,
+    /// After an `ESC`.
+    Escape,
+    /// Inside a `CSI` sequence, collecting the parameters and intermediate bytes.
+    Csi(Vec<u8>),
+    /// Inside a `CSI` sequence which is too long, and is ignored.
+    CsiIgnore,
+    /// Inside an `OSC` (or other string) sequence, which is ignored.
+    Osc,
+    /// After an `ESC` inside an `OSC` sequence, possibly terminating it.
+    OscEscape,
+}
+
+/// An in-memory terminal emulator.
+///
+/// See the module documentation for details.
+#[derive(Clone, Debug)]
+pub struct VirtualTerminal {
+    cols: u16,
+    rows: u16,
+    main: Grid,
+    alternate: Grid,
+    alternate_active: bool,
+    x: u16,
+    y: u16,
+    wrap_pending: bool,
+    cursor_visible: bool,
+    pen: Pen,
+    state: State,
+    /// The incomplete UTF-8 sequence at the end of the last write.
+    utf8: Vec<u8>,
+}
+
+impl VirtualTerminal {
+    /// Create a virtual terminal with the given size, in columns and rows.
+    pub fn new(cols: u16, rows: u16) -> VirtualTerminal {
+        assert!(cols > 0 && rows > 0, "The terminal must have at least one cell.");
+
+        VirtualTerminal {
+            cols: cols,
+            rows: rows,
+            main: Grid::new(cols, rows),
+            alternate: Grid::new(cols, rows),
+            alternate_active: false,
+            x: 0,
+            y: 0,
+            wrap_pending: false,
+            cursor_visible: true,
+            pen: Pen::default(),
+            state: State::Ground,
+            utf8: Vec::new(),
+        }
+    }
+
+    /// Get the size of the terminal, in columns and rows.
+    pub fn size(&self) -> (u16, u16) {
+        (self.cols, self.rows)
+    }
+
+    /// Get the (1,1)-based cursor position.
+    pub fn cursor(&self) -> (u16, u16) {
+        (self.x + 1, self.y + 1)
+    }
+
+    /// Is the cursor visible?
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Is the alternate screen active?
+    pub fn is_alternate_screen(&self) -> bool {
+        self.alternate_active
+    }
+
+    /// Get the cell at the given (1,1)-based position of the active screen.
+    pub fn cell(&self, x: u16, y: u16) -> Option<&Cell> {
+        if x == 0 || y == 0 {
+            return None;
+        }
+        self.grid().rows.get(y as usize - 1).and_then(|row| row.get(x as usize - 1))
+    }
+
+    /// Get the text of the given (1,1)-based line of the active screen, without trailing
+    /// whitespace.
+    pub fn line(&self, y: u16) -> Option<String> {
+        if y == 0 {
+            return None;
+        }
+        self.grid().rows.get(y as usize - 1).map(|row| {
+            let line: String = row.iter().map(|cell| cell.ch).collect();
+            line.trim_end().to_owned()
+        })
+    }
+
+    /// Get the text of the active screen, one line per row, without trailing whitespace or
+    /// trailing empty lines.
+    pub fn contents(&self) -> String {
+        let lines: Vec<String> = (1..self.rows + 1).filter_map(|y| self.line(y)).collect();
+        let mut contents = lines.join("\n");
+        let len = contents.trim_end_matches('\n').len();
+        contents.truncate(len);
+        contents
+    }
+
+    fn grid(&self) -> &Grid {
+        if self.alternate_active {
+            &self.alternate
+        } else {
+            &self.main
+        }
+    }
+
+    fn grid_mut(&mut self) -> &mut Grid {
+        if self.alternate_active {
+            &mut self.alternate
+        } else {
+            &mut self.main
+        }
+    }
+
+    /// A blank cell, using the current background color.
+    fn blank(&self) -> Cell {
+        Cell { bg: self.pen.bg, ..Cell::default() }
+    }
+
+    fn feed(&mut self, byte: u8) {
+        let state = mem::replace(&mut self.state, State::Ground);
+        self.state = match state {
+            State::Ground => self.ground(byte),
+            State::Escape => self.escape(byte),
+            State::Csi(mut buf) => {
+                match byte {
+                    0x20..=0x3F if buf.len() < MAX_CSI_LEN => {
+                        buf.push(byte);
+                        State::Csi(buf)
+                    }
+                    0x20..=0x3F => State::CsiIgnore,
+                    0x40..=0x7E => {
+                        self.csi(&buf, byte);
+                        State::Ground
+                    }
+                    0x1B => State::Escape,
+                    // Control characters are executed in the middle of sequences.
+                    0x00..=0x1F => {
+                        self.control(byte);
+                        State::Csi(buf)
+                    }
+                    _ => State::Ground,
+                }
+            }
+            State::CsiIgnore => {
+                match byte {
+                    0x20..=0x3F => State::CsiIgnore,
+                    0x40..=0x7E => State::Ground,
+                    0x1B => State::Escape,
+                    0x00..=0x1F => {
+                        self.control(byte);
+                        State::CsiIgnore
+                    }
+                    _ => State::Ground,
+                }
+            }
+            State::Osc => {
+                match byte {
+                    0x07 => State::Ground,
+                    0x1B => State::OscEscape,
+                    _ => State::Osc,
+                }
+            }
+            State::OscEscape => {
+                match byte {
+                    b'\\' => State::Ground,
+                    0x1B => State::OscEscape,
+                    _ => State::Osc,
+                }
+            }
+        };
+    }
+
+    fn ground(&mut self, byte: u8) -> State {
+        if !self.utf8.is_empty() || byte >= 0x80 {
+            self.utf8.push(byte);
+            let ch = match str::from_utf8(&self.utf8) {
+                Ok(s) => s.chars().next().unwrap(),
+                // Wait for the rest of the character.
+                Err(ref e) if e.error_len().is_none() => return State::Ground,
+                Err(_) => '\u{FFFD}',
+            };
+            self.utf8.clear();
+            self.print(ch);
+        } else if byte == 0x1B {
+            return State::Escape;
+        } else if byte < 0x20 || byte == 0x7F {
+            self.control(byte);
+        } else {
+            self.print(byte as char);
+        }
+        State::Ground
+    }
+
+    fn control(&mut self, byte: u8) {
+        match byte {
+            // Backspace.
+            0x08 => {
+                self.x = self.x.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            // Horizontal tab.
+            0x09 => {
+                self.x = cmp::min((self.x / 8 + 1) * 8, self.cols - 1);
+                self.wrap_pending = false;
+            }
+            // Line feed, vertical tab and form feed.
+            0x0A..=0x0C => self.line_feed(),
+            // Carriage return.
+            0x0D => {
+                self.x = 0;
+                self.wrap_pending = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn escape(&mut self, byte: u8) -> State {
+        match byte {
+            b'[' => return State::Csi(Vec::new()),
+            // OSC, DCS, APC, PM and SOS are all ignored strings.
+            b']' | b'P' | b'_' | b'^' | b'X' => return State::Osc,
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.x = 0;
+                self.line_feed();
+            }
+            b'M' => self.reverse_line_feed(),
+            b'c' => *self = VirtualTerminal::new(self.cols, self.rows),
+            _ => {}
+        }
+        State::Ground
+    }
+
+    fn csi(&mut self, buf: &[u8], action: u8) {
+        // Sequences with intermediate bytes are all unsupported.
+        if buf.iter().any(|&b| b < 0x30) {
+            return;
+        }
+        // A private marker (`<`, `=`, `>` or `?`) can only come first.
+        let (marker, buf) = match buf.split_first() {
+            Some((&b, rest)) if b >= b'<' => (Some(b), rest),
+            _ => (None, buf),
+        };
+        let params: Vec<u16> = String::from_utf8_lossy(buf)
+            .split(';')
+            .map(|n| n.parse().unwrap_or(0))
+            .collect();
+        // The first parameter, with zero meaning the default of one.
+        let n = cmp::max(params[0], 1);
+
+        // Only the DEC private modes are supported among the private sequences.
+        if let Some(marker) = marker {
+            match (marker, action) {
+                (b'?', b'h') => self.set_modes(&params, true),
+                (b'?', b'l') => self.set_modes(&params, false),
+                _ => {}
+            }
+            return;
+        }
+
+        self.wrap_pending = false;
+        match action {
+            b'A' => self.y = self.y.saturating_sub(n),
+            b'B' => self.y = cmp::min(self.y.saturating_add(n), self.rows - 1),
+            b'C' => self.x = cmp::min(self.x.saturating_add(n), self.cols - 1),
+            b'D' => self.x = self.x.saturating_sub(n),
+            b'E' => {
+                self.x = 0;
+                self.y = cmp::min(self.y.saturating_add(n), self.rows - 1);
+            }
+            b'F' => {
+                self.x = 0;
+                self.y = self.y.saturating_sub(n);
+            }
+            b'G' => self.x = cmp::min(n, self.cols) - 1,
+            b'd' => self.y = cmp::min(n, self.rows) - 1,
+            b'H' | b'f' => {
+                let x = params.get(1).map_or(1, |&x| cmp::max(x, 1));
+                self.y = cmp::min(n, self.rows) - 1;
+                self.x = cmp::min(x, self.cols) - 1;
+            }
+            b'J' => {
+                match params[0] {
+                    0 => {
+                        self.erase_line(self.x, self.cols);
+                        self.erase_rows(self.y + 1, self.rows);
+                    }
+                    1 => {
+                        self.erase_rows(0, self.y);
+                        self.erase_line(0, self.x + 1);
+                    }
+                    2 | 3 => self.erase_rows(0, self.rows),
+                    _ => {}
+                }
+            }
+            b'K' => {
+                match params[0] {
+                    0 => self.erase_line(self.x, self.cols),
+                    1 => self.erase_line(0, self.x + 1),
+                    2 => self.erase_line(0, self.cols),
+                    _ => {}
+                }
+            }
+            b'S' => self.scroll_up(n),
+            b'T' => self.scroll_down(n),
+            b'm' => {
+                // SGR parameters may have colon-separated sub-parameters, e.g. `4:3` or
+                // `58:2::r:g:b`.
+                let groups: Vec<Vec<u16>> = String::from_utf8_lossy(buf)
+                    .split(';')
+                    .map(|p| p.split(':').map(|n| n.parse().unwrap_or(0)).collect())
+                    .collect();
+                self.sgr(&groups)
+            }
+            b's' => self.save_cursor(),
+            b'u' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn set_modes(&mut self, params: &[u16], enable: bool) {
+        for &mode in params {
+            match mode {
+                25 => self.cursor_visible = enable,
+                47 | 1047 => self.switch_screen(enable),
+                1049 => {
+                    if enable {
+                        self.save_cursor();
+                        self.switch_screen(true);
+                        self.erase_rows(0, self.rows);
+                    } else {
+                        self.switch_screen(false);
+                        self.restore_cursor();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn sgr(&mut self, params: &[Vec<u16>]) {
+        let mut params = params.iter();
+        while let Some(group) = params.next() {
+            let param = group[0];
+            if group.len() > 1 {
+                self.sgr_sub_params(group);
+                continue;
+            }
+
+            match param {
+                0 => self.pen = Pen::default(),
+                1 => self.pen.attrs.insert(Attributes::BOLD),
+                2 => self.pen.attrs.insert(Attributes::FAINT),
+                3 => self.pen.attrs.insert(Attributes::ITALIC),
+                4 => self.pen.attrs.insert(Attributes::UNDERLINE),
+                5 => self.pen.attrs.insert(Attributes::BLINK),
+                6 => self.pen.attrs.insert(Attributes::RAPID_BLINK),
+                7 => self.pen.attrs.insert(Attributes::INVERT),
+                8 => self.pen.attrs.insert(Attributes::HIDDEN),
+                9 => self.pen.attrs.insert(Attributes::CROSSED_OUT),
+                21 => self.pen.attrs.insert(Attributes::DOUBLE_UNDERLINE),
+                22 => self.pen.attrs.remove(Attributes::BOLD | Attributes::FAINT),
+                23 => self.pen.attrs.remove(Attributes::ITALIC),
+                24 => self.pen.attrs.remove(underlines()),
+                25 => self.pen.attrs.remove(Attributes::BLINK | Attributes::RAPID_BLINK),
+                27 => self.pen.attrs.remove(Attributes::INVERT),
+                28 => self.pen.attrs.remove(Attributes::HIDDEN),
+                29 => self.pen.attrs.remove(Attributes::CROSSED_OUT),
+                51 => self.pen.attrs.insert(Attributes::FRAMED),
+                52 => self.pen.attrs.insert(Attributes::ENCIRCLED),
+                53 => self.pen.attrs.insert(Attributes::OVERLINE),
+                54 => self.pen.attrs.remove(Attributes::FRAMED | Attributes::ENCIRCLED),
+                55 => self.pen.attrs.remove(Attributes::OVERLINE),
+                73 => self.pen.attrs.insert(Attributes::SUPERSCRIPT),
+                74 => self.pen.attrs.insert(Attributes::SUBSCRIPT),
+                75 => self.pen.attrs.remove(Attributes::SUPERSCRIPT | Attributes::SUBSCRIPT),
+                30..=37 => self.pen.fg = AnyColor::named(param as u8 - 30),
+                90..=97 => self.pen.fg = AnyColor::named(param as u8 - 90 + 8),
+                40..=47 => self.pen.bg = AnyColor::named(param as u8 - 40),
+                100..=107 => self.pen.bg = AnyColor::named(param as u8 - 100 + 8),
+                39 => self.pen.fg = AnyColor::Reset,
+                49 => self.pen.bg = AnyColor::Reset,
+                38 => {
+                    if let Some(color) = extended_color(&mut params.by_ref().map(|g| g[0])) {
+                        self.pen.fg = color;
+                    }
+                }
+                48 => {
+                    if let Some(color) = extended_color(&mut params.by_ref().map(|g| g[0])) {
+                        self.pen.bg = color;
+                    }
+                }
+                // The underline color is not tracked, but its parameters must be skipped.
+                58 => {
+                    extended_color(&mut params.by_ref().map(|g| g[0]));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Handle an SGR parameter with colon-separated sub-parameters.
+    fn sgr_sub_params(&mut self, group: &[u16]) {
+        match group[0] {
+            4 => {
+                self.pen.attrs.remove(underlines());
+                match group[1] {
+                    1 => self.pen.attrs.insert(Attributes::UNDERLINE),
+                    2 => self.pen.attrs.insert(Attributes::DOUBLE_UNDERLINE),
+                    3 => self.pen.attrs.insert(Attributes::CURLY_UNDERLINE),
+                    4 => self.pen.attrs.insert(Attributes::DOTTED_UNDERLINE),
+                    5 => self.pen.attrs.insert(Attributes::DASHED_UNDERLINE),
+                    _ => {}
+                }
+            }
+            38 | 48 => {
+                let mut sub = group[1..].to_vec();
+                // Truecolor may have a color space identifier: `38:2:id:r:g:b`.
+                if sub.len() == 5 && sub[0] == 2 {
+                    sub.remove(1);
+                }
+                if let Some(color) = extended_color(&mut sub.into_iter()) {
+                    if group[0] == 38 {
+                        self.pen.fg = color;
+                    } else {
+                        self.pen.bg = color;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn print(&mut self, ch: char) {
+        if self.wrap_pending {
+            self.wrap_pending = false;
+            self.x = 0;
+            self.line_feed();
+        }
+
+        let (x, y) = (self.x as usize, self.y as usize);
+        let pen = self.pen;
+        self.grid_mut().rows[y][x] = Cell {
+            ch: ch,
+            fg: pen.fg,
+            bg: pen.bg,
+            attrs: pen.attrs,
+        };
+
+        if self.x + 1 == self.cols {
+            self.wrap_pending = true;
+        } else {
+            self.x += 1;
+        }
+    }
+
+    fn line_feed(&mut self) {
+        self.wrap_pending = false;
+        if self.y + 1 == self.rows {
+            self.scroll_up(1);
+        } else {
+            self.y += 1;
+        }
+    }
+
+    fn reverse_line_feed(&mut self) {
+        self.wrap_pending = false;
+        if self.y == 0 {
+            self.scroll_down(1);
+        } else {
+            self.y -= 1;
+        }
+    }
+
+    /// Move the content up by `n` lines, inserting blank lines at the bottom.
+    fn scroll_up(&mut self, n: u16) {
+        let n = cmp::min(n, self.rows) as usize;
+        let blank = vec![self.blank(); self.cols as usize];
+        let rows = &mut self.grid_mut().rows;
+        rows.drain(..n);
+        rows.extend((0..n).map(|_| blank.clone()));
+    }
+
+    /// Move the content down by `n` lines, inserting blank lines at the top.
+    fn scroll_down(&mut self, n: u16) {
+        let n = cmp::min(n, self.rows) as usize;
+        let blank = vec![self.blank(); self.cols as usize];
+        let rows = &mut self.grid_mut().rows;
+        let len = rows.len();
+        rows.truncate(len - n);
+        for _ in 0..n {
+            rows.insert(0, blank.clone());
+        }
+    }
+
+    /// Erase the rows in `from..to`.
+    fn erase_rows(&mut self, from: u16, to: u16) {
+        let blank = self.blank();
+        for row in &mut self.grid_mut().rows[from as usize..to as usize] {
+            for cell in row.iter_mut() {
+                *cell = blank;
+            }
+        }
+    }
+
+    /// Erase the columns in `from..to` of the cursor line.
+    fn erase_line(&mut self, from: u16, to: u16) {
+        let blank = self.blank();
+        let y = self.y as usize;
+        for cell in &mut self.grid_mut().rows[y][from as usize..to as usize] {
+            *cell = blank;
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        let saved = SavedCursor {
+            x: self.x,
+            y: self.y,
+            pen: self.pen,
+        };
+        self.grid_mut().saved = saved;
+    }
+
+    fn restore_cursor(&mut self) {
+        let saved = self.grid().saved;
+        self.x = saved.x;
+        self.y = saved.y;
+        self.pen = saved.pen;
+        self.wrap_pending = false;
+    }
+
+    fn switch_screen(&mut self, alternate: bool) {
+        self.alternate_active = alternate;
+        self.wrap_pending = false;
+    }
+}
+
+/// All the underline styles.
+fn underlines() -> Attributes {
+    Attributes::UNDERLINE | Attributes::DOUBLE_UNDERLINE | Attributes::CURLY_UNDERLINE |
+    Attributes::DOTTED_UNDERLINE | Attributes::DASHED_UNDERLINE
+}
+
+/// Parse the `5;n` or `2;r;g;b` parameters of an extended SGR color.
+fn extended_color<I: Iterator<Item = u16>>(params: &mut I) -> Option<AnyColor> {
+    match params.next() {
+        Some(5) => params.next().map(|n| AnyColor::Ansi(AnsiValue(n as u8))),
+        Some(2) => {
+            let r = params.next()? as u8;
+            let g = params.next()? as u8;
+            let b = params.next()? as u8;
+            Some(AnyColor::Rgb(Rgb(r, g, b)))
+        }
+        _ => None,
+    }
+}
+
+impl Write for VirtualTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.feed(byte);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use {clear, color, cursor, screen, scroll, style};
+
+    #[test]
+    fn test_text() {
+        let mut term = VirtualTerminal::new(10, 3);
+        write!(term, "hello\r\nwörld").unwrap();
+
+        assert_eq!(term.contents(), "hello\nwörld");
+        assert_eq!(term.cursor(), (6, 2));
+    }
+
+    #[test]
+    fn test_split_utf8() {
+        let mut term = VirtualTerminal::new(10, 3);
+        let bytes = "é€".as_bytes();
+        for b in bytes {
+            term.write_all(&[*b]).unwrap();
+        }
+
+        assert_eq!(term.line(1).unwrap(), "é€");
+    }
+
+    #[test]
+    fn test_wrap_and_scroll() {
+        let mut term = VirtualTerminal::new(4, 2);
+        write!(term, "abcdefghij").unwrap();
+
+        assert_eq!(term.contents(), "efgh\nij");
+        assert_eq!(term.cursor(), (3, 2));
+
+        // The cursor stays on the last column until the next character.
+        write!(term, "{}xy", cursor::Goto(1, 1)).unwrap();
+        write!(term, "{}z", cursor::Goto(4, 1)).unwrap();
+        assert_eq!(term.cursor(), (4, 1));
+        assert_eq!(term.line(1).unwrap(), "xygz");
+    }
+
+    #[test]
+    fn test_cursor_movement() {
+        let mut term = VirtualTerminal::new(10, 5);
+        write!(term, "{}", cursor::Goto(5, 3)).unwrap();
+        assert_eq!(term.cursor(), (5, 3));
+        write!(term, "{}{}", cursor::Up(1), cursor::Left(2)).unwrap();
+        assert_eq!(term.cursor(), (3, 2));
+        write!(term, "{}{}", cursor::Down(10), cursor::Right(10)).unwrap();
+        assert_eq!(term.cursor(), (10, 5));
+
+        write!(term, "{}{}{}", cursor::Save, cursor::Goto(1, 1), cursor::Restore).unwrap();
+        assert_eq!(term.cursor(), (10, 5));
+
+        assert!(term.is_cursor_visible());
+        write!(term, "{}", cursor::Hide).unwrap();
+        assert!(!term.is_cursor_visible());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut term = VirtualTerminal::new(5, 3);
+        write!(term, "aaaaabbbbbccccc").unwrap();
+
+        write!(term, "{}{}", cursor::Goto(3, 2), clear::UntilNewline).unwrap();
+        assert_eq!(term.contents(), "aaaaa\nbb\nccccc");
+        write!(term, "{}", clear::BeforeCursor).unwrap();
+        assert_eq!(term.contents(), "\n\nccccc");
+        write!(term, "{}", clear::All).unwrap();
+        assert_eq!(term.contents(), "");
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut term = VirtualTerminal::new(5, 3);
+        write!(term, "a\r\nb\r\nc").unwrap();
+
+        write!(term, "{}", scroll::Up(1)).unwrap();
+        assert_eq!(term.contents(), "b\nc");
+        write!(term, "{}", scroll::Down(2)).unwrap();
+        assert_eq!(term.contents(), "\n\nb");
+    }
+
+    #[test]
+    fn test_sgr() {
+        let mut term = VirtualTerminal::new(10, 1);
+        write!(term,
+               "{}{}{}a{}b{}c",
+               color::Fg(color::Red),
+               color::Bg(color::Rgb(1, 2, 3)),
+               style::Bold,
+               style::Italic,
+               style::Reset)
+            .unwrap();
+
+        let a = *term.cell(1, 1).unwrap();
+        assert_eq!(a.fg, AnyColor::Red);
+        assert_eq!(a.bg, AnyColor::Rgb(Rgb(1, 2, 3)));
+        assert_eq!(a.attrs, Attributes::BOLD);
+        assert_eq!(term.cell(2, 1).unwrap().attrs,
+                   Attributes::BOLD | Attributes::ITALIC);
+        assert_eq!(*term.cell(3, 1).unwrap(), Cell { ch: 'c', ..Cell::default() });
+    }
+
+    #[test]
+    fn test_sgr_attributes() {
+        let mut term = VirtualTerminal::new(10, 1);
+        write!(term,
+               "{}{}{}a{}{}b{}c",
+               style::Bold,
+               style::Faint,
+               style::DoubleUnderline,
+               style::NoBold,
+               style::Overline,
+               style::NoUnderline)
+            .unwrap();
+
+        assert_eq!(term.cell(1, 1).unwrap().attrs,
+                   Attributes::BOLD | Attributes::FAINT | Attributes::DOUBLE_UNDERLINE);
+        assert_eq!(term.cell(2, 1).unwrap().attrs,
+                   Attributes::DOUBLE_UNDERLINE | Attributes::OVERLINE);
+        assert_eq!(term.cell(3, 1).unwrap().attrs, Attributes::OVERLINE);
+    }
+
+    #[test]
+    fn test_sgr_sub_params() {
+        let mut term = VirtualTerminal::new(10, 1);
+        write!(term,
+               "{}{}a{}b\x1B[38:2::1:2:3mc{}d",
+               style::CurlyUnderline,
+               color::UnderlineColor(color::Rgb(1, 2, 3)),
+               style::DottedUnderline,
+               style::NoUnderline)
+            .unwrap();
+
+        assert_eq!(term.cell(1, 1).unwrap().attrs, Attributes::CURLY_UNDERLINE);
+        assert_eq!(term.cell(1, 1).unwrap().fg, AnyColor::Reset);
+        assert_eq!(term.cell(2, 1).unwrap().attrs, Attributes::DOTTED_UNDERLINE);
+        assert_eq!(term.cell(3, 1).unwrap().fg, AnyColor::Rgb(Rgb(1, 2, 3)));
+        assert_eq!(term.cell(4, 1).unwrap().attrs, Attributes::empty());
+    }
+
+    #[test]
+    fn test_alternate_screen() {
+        let mut term = VirtualTerminal::new(10, 2);
+        write!(term, "main{}", cursor::Goto(2, 2)).unwrap();
+
+        write!(term, "{}alt", screen::ToAlternateScreen).unwrap();
+        assert!(term.is_alternate_screen());
+        assert_eq!(term.contents(), "\n alt");
+
+        write!(term, "{}", screen::ToMainScreen).unwrap();
+        assert!(!term.is_alternate_screen());
+        assert_eq!(term.contents(), "main");
+        assert_eq!(term.cursor(), (2, 2));
+    }
+
+    #[test]
+    fn test_ignored_sequences() {
+        let mut term = VirtualTerminal::new(10, 1);
+        write!(term, "a\x1B]4;1;?\x07b\x1B]0;title\x1B\\c\x1B[?1000hd").unwrap();
+
+        assert_eq!(term.contents(), "abcd");
+
+        // Private sequences, and sequences with intermediate bytes.
+        write!(term, "\x1B[>4;1m\x1B[=1m\x1B[<1m\x1B[?1m\x1B[1 qe").unwrap();
+        assert_eq!(term.cell(5, 1).unwrap().attrs, Attributes::empty());
+        assert_eq!(term.cursor(), (6, 1));
+
+        // Overlong sequences.
+        write!(term, "\x1B[{}1mf", "1;".repeat(1000)).unwrap();
+        assert_eq!(term.cell(6, 1).unwrap().attrs, Attributes::empty());
+        assert_eq!(term.contents(), "abcdef");
+    }
+}